@@ -0,0 +1,319 @@
+// src/adb/backend.rs
+use super::{
+    Device, DeviceSnapshot, DeviceUser, InstalledPackage, PackageMetadata, PmOutcome, PurgeAction,
+    RestrictedPackages, ServerStatus, PRIMARY_USER,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How often the default [`DeviceBackend::watch_devices`] lists devices.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Everything the GUI and CLI need from a device. [`AdbBackend`] talks to real
+/// hardware; [`super::FakeBackend`] serves a fixture so both front ends can be
+/// run without a phone.
+///
+/// Package methods take the id of the user or profile to act on; see
+/// [`DeviceBackend::list_users`].
+pub trait DeviceBackend: Send + Sync {
+    fn list_devices(&self) -> Result<Vec<Device>, String>;
+    fn device_model(&self, serial: &str) -> Result<String, String>;
+    fn list_users(&self, serial: &str) -> Result<Vec<DeviceUser>, String>;
+    fn list_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String>;
+    /// Packages that still exist on the system image but are no longer
+    /// installed for `user`.
+    fn list_removed_packages(
+        &self,
+        serial: &str,
+        user: u32,
+        installed: &[String],
+    ) -> Result<Vec<String>, String>;
+    fn list_disabled_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String>;
+    fn list_restricted_packages(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<RestrictedPackages, String>;
+    fn list_package_details(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<Vec<InstalledPackage>, String>;
+    fn package_metadata(
+        &self,
+        serial: &str,
+        user: u32,
+        package: &str,
+    ) -> Result<PackageMetadata, String>;
+    fn purge(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome;
+    /// Undoes `action` for `package`: reinstalls, enables, unsuspends or unhides it.
+    fn revert(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome;
+    fn reboot(&self, serial: &str);
+    fn server_status(&self) -> Result<ServerStatus, String>;
+    /// Kills the running adb server, whoever started it, and starts a new
+    /// one.
+    fn restart_server(&self) -> Result<ServerStatus, String>;
+    /// Pairs with an Android 11+ device's wireless debugging service.
+    fn pair(&self, address: &str, code: &str) -> Result<String, String>;
+    /// Connects to a device over TCP/IP and returns its normalized address.
+    fn connect(&self, address: &str) -> Result<String, String>;
+    /// Disconnects `address`, or every TCP/IP device when `None`.
+    fn disconnect(&self, address: Option<&str>) -> Result<(), String>;
+    /// Switches a USB device to TCP/IP on `port` and returns the address to
+    /// connect to, when its Wi-Fi address is known.
+    fn enable_tcpip(&self, serial: &str, port: u16) -> Result<Option<String>, String>;
+    /// Device paths of the package's APKs, base APK first.
+    fn apk_paths(&self, serial: &str, user: u32, package: &str) -> Result<Vec<String>, String>;
+    fn pull_file(&self, serial: &str, remote: &str, local: &Path) -> Result<(), String>;
+    /// Installs `apks` as one package for `user`, replacing any installed
+    /// version.
+    fn install_apks(&self, serial: &str, user: u32, package: &str, apks: &[PathBuf]) -> PmOutcome;
+
+    /// Blocks, calling `on_change` with the device list once and then
+    /// whenever a device attaches, detaches or changes state, until it
+    /// returns `false`. Polls [`DeviceBackend::list_devices`] by default.
+    fn watch_devices(&self, on_change: &mut dyn FnMut(Vec<Device>) -> bool) -> Result<(), String> {
+        poll_devices(self, on_change)
+    }
+
+    fn detect_device(&self, serial: &str) -> Result<(), String> {
+        let devices = self.list_devices()?;
+        match devices.iter().find(|d| d.serial == serial) {
+            Some(device) if device.is_authorized() => Ok(()),
+            Some(device) => Err(device_problem(device)),
+            None => Err(format!("Device {} is no longer connected.", serial)),
+        }
+    }
+
+    fn load_device(&self, serial: &str, user: u32) -> Result<DeviceSnapshot, String> {
+        self.detect_device(serial)?;
+        let model = self.device_model(serial)?;
+        let users = self.list_users(serial)?;
+        if !users.iter().any(|u| u.id == user) {
+            return Err(format!("User {} does not exist on {}.", user, serial));
+        }
+        let installed = self.list_packages(serial, user)?;
+        let restricted = self.list_restricted_packages(serial, user)?;
+        // Hidden packages are missing from the installed list too.
+        let removed = self
+            .list_removed_packages(serial, user, &installed)?
+            .into_iter()
+            .filter(|package| !restricted.hidden.contains(package))
+            .collect();
+        let disabled = self.list_disabled_packages(serial, user)?;
+        let details = self.list_package_details(serial, user)?;
+
+        let mut installed_for: HashMap<String, Vec<u32>> = HashMap::new();
+        if users.len() > 1 {
+            for other in &users {
+                let packages = if other.id == user {
+                    installed.clone()
+                } else {
+                    self.list_packages(serial, other.id)?
+                };
+                for package in packages {
+                    installed_for.entry(package).or_default().push(other.id);
+                }
+            }
+        }
+
+        Ok(DeviceSnapshot {
+            model,
+            user,
+            users,
+            installed,
+            removed,
+            hidden: restricted.hidden,
+            disabled,
+            suspended: restricted.suspended,
+            details,
+            installed_for,
+        })
+    }
+
+    /// Picks the device to talk to: the requested serial if given, otherwise
+    /// the only authorized device attached.
+    fn resolve_serial(&self, requested: Option<&str>) -> Result<String, String> {
+        if let Some(serial) = requested {
+            self.detect_device(serial)?;
+            return Ok(serial.to_string());
+        }
+        let devices = self.list_devices()?;
+        let authorized: Vec<&Device> = devices.iter().filter(|d| d.is_authorized()).collect();
+        match authorized.as_slice() {
+            [] => Err(match devices.first() {
+                Some(device) => device_problem(device),
+                None => format!("No device found. {}", super::NO_DEVICE_GUIDANCE),
+            }),
+            [device] => Ok(device.serial.clone()),
+            _ => Err("Multiple devices connected; pass --serial to pick one.".to_string()),
+        }
+    }
+
+    /// The requested user if it exists on the device, otherwise the primary
+    /// user.
+    fn resolve_user(&self, serial: &str, requested: Option<u32>) -> Result<u32, String> {
+        let Some(user) = requested else {
+            return Ok(PRIMARY_USER);
+        };
+        let users = self.list_users(serial)?;
+        if users.iter().any(|u| u.id == user) {
+            Ok(user)
+        } else {
+            let known: Vec<String> = users.iter().map(DeviceUser::display_name).collect();
+            Err(format!(
+                "User {} does not exist on {}; available users: {}.",
+                user,
+                serial,
+                known.join(", ")
+            ))
+        }
+    }
+}
+
+/// Why `device` can't be used, and what to do about it.
+pub fn device_problem(device: &Device) -> String {
+    let mut message = format!("Device {} is {}.", device.serial, device.state);
+    if let Some(guidance) = device.state.guidance() {
+        message.push(' ');
+        message.push_str(&guidance);
+    }
+    message
+}
+
+/// Calls `on_change` whenever [`DeviceBackend::list_devices`] returns
+/// something new, checking every [`DEVICE_POLL_INTERVAL`].
+fn poll_devices<B: DeviceBackend + ?Sized>(
+    backend: &B,
+    on_change: &mut dyn FnMut(Vec<Device>) -> bool,
+) -> Result<(), String> {
+    let mut last: Option<Vec<Device>> = None;
+    loop {
+        let devices = backend.list_devices()?;
+        if last.as_ref() != Some(&devices) {
+            if !on_change(devices.clone()) {
+                return Ok(());
+            }
+            last = Some(devices);
+        }
+        thread::sleep(DEVICE_POLL_INTERVAL);
+    }
+}
+
+/// Real devices, through the adb server.
+pub struct AdbBackend;
+
+impl DeviceBackend for AdbBackend {
+    fn list_devices(&self) -> Result<Vec<Device>, String> {
+        super::list_devices()
+    }
+
+    fn device_model(&self, serial: &str) -> Result<String, String> {
+        super::get_device_model(serial)
+    }
+
+    fn list_users(&self, serial: &str) -> Result<Vec<DeviceUser>, String> {
+        super::list_users(serial)
+    }
+
+    fn list_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String> {
+        super::list_packages(serial, user)
+    }
+
+    fn list_removed_packages(
+        &self,
+        serial: &str,
+        user: u32,
+        installed: &[String],
+    ) -> Result<Vec<String>, String> {
+        super::list_removed_packages(serial, user, installed)
+    }
+
+    fn list_disabled_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String> {
+        super::list_disabled_packages(serial, user)
+    }
+
+    fn list_restricted_packages(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<RestrictedPackages, String> {
+        super::list_restricted_packages(serial, user)
+    }
+
+    fn list_package_details(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<Vec<InstalledPackage>, String> {
+        super::list_package_details(serial, user)
+    }
+
+    fn package_metadata(
+        &self,
+        serial: &str,
+        user: u32,
+        package: &str,
+    ) -> Result<PackageMetadata, String> {
+        super::package_metadata(serial, user, package)
+    }
+
+    fn purge(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+        super::purge(serial, user, package, action)
+    }
+
+    fn revert(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+        super::revert(serial, user, package, action)
+    }
+
+    fn reboot(&self, serial: &str) {
+        super::reboot_device(serial)
+    }
+
+    fn server_status(&self) -> Result<ServerStatus, String> {
+        super::server_status()
+    }
+
+    fn restart_server(&self) -> Result<ServerStatus, String> {
+        super::restart_server()
+    }
+
+    fn watch_devices(&self, on_change: &mut dyn FnMut(Vec<Device>) -> bool) -> Result<(), String> {
+        // Servers that refuse `track-devices` can still be polled.
+        if super::track_devices(on_change)? {
+            Ok(())
+        } else {
+            poll_devices(self, on_change)
+        }
+    }
+
+    fn pair(&self, address: &str, code: &str) -> Result<String, String> {
+        super::pair(address, code)
+    }
+
+    fn connect(&self, address: &str) -> Result<String, String> {
+        super::connect(address)
+    }
+
+    fn disconnect(&self, address: Option<&str>) -> Result<(), String> {
+        super::disconnect(address)
+    }
+
+    fn enable_tcpip(&self, serial: &str, port: u16) -> Result<Option<String>, String> {
+        super::enable_tcpip(serial, port)
+    }
+
+    fn apk_paths(&self, serial: &str, user: u32, package: &str) -> Result<Vec<String>, String> {
+        super::apk_paths(serial, user, package)
+    }
+
+    fn pull_file(&self, serial: &str, remote: &str, local: &Path) -> Result<(), String> {
+        super::pull_file(serial, remote, local)
+    }
+
+    fn install_apks(&self, serial: &str, user: u32, _package: &str, apks: &[PathBuf]) -> PmOutcome {
+        super::install_apks(serial, user, apks)
+    }
+}
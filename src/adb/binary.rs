@@ -0,0 +1,242 @@
+// src/adb/binary.rs
+//! Picks the `adb` executable that starts the server: the one named by
+//! `PURGE_ADB`, otherwise the newer of the bundled binary and an `adb` on
+//! `PATH`.
+//!
+//! The bundled files are extracted once per process into a per-user cache
+//! folder named after the app version. A file already there is only reused
+//! when its SHA-256 matches the embedded bytes; anything else is rewritten
+//! through a temporary file and a rename, so a crash or a second instance
+//! never leaves a half-written binary behind.
+use include_dir::{include_dir, Dir};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[cfg(target_os = "windows")]
+const ADB_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/vendor/win");
+#[cfg(target_os = "linux")]
+const ADB_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/vendor/linux");
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+compile_error!("This application is only supported on Windows and Linux.");
+
+#[cfg(target_os = "windows")]
+const ADB_EXECUTABLE: &str = "adb.exe";
+#[cfg(not(target_os = "windows"))]
+const ADB_EXECUTABLE: &str = "adb";
+
+/// Environment variable naming an `adb` executable to use instead of the
+/// bundled one.
+pub const ADB_PATH_ENV: &str = "PURGE_ADB";
+
+/// `adb version` can hang on a broken install; don't wait on it forever.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The executable servers are started with.
+pub struct Adb {
+    pub path: PathBuf,
+    version: Option<AdbVersion>,
+}
+
+impl Adb {
+    fn new(path: PathBuf) -> Self {
+        let version = version(&path);
+        Self { path, version }
+    }
+
+    /// The protocol version a server started by this adb reports; see
+    /// [`super::client::server_version`].
+    pub fn server_version(&self) -> Option<u32> {
+        self.version.as_ref()?.protocol.last().copied()
+    }
+}
+
+/// The executable to run, chosen on first use and kept for the rest of the
+/// process.
+pub fn adb() -> Result<&'static Adb, String> {
+    static CHOSEN: OnceLock<Result<Adb, String>> = OnceLock::new();
+    CHOSEN.get_or_init(choose).as_ref().map_err(Clone::clone)
+}
+
+fn choose() -> Result<Adb, String> {
+    if let Some(configured) = std::env::var_os(ADB_PATH_ENV) {
+        let path = PathBuf::from(configured);
+        return if path.is_file() {
+            Ok(Adb::new(path))
+        } else {
+            Err(format!(
+                "{} is set to {}, which is not a file.",
+                ADB_PATH_ENV,
+                path.display()
+            ))
+        };
+    }
+    match (extract_bundled(), find_on_path()) {
+        (Ok(bundled), Some(system)) => {
+            let (bundled, system) = (Adb::new(bundled), Adb::new(system));
+            if system.version.cmp(&bundled.version) == Ordering::Greater {
+                Ok(system)
+            } else {
+                Ok(bundled)
+            }
+        }
+        (Ok(bundled), None) => Ok(Adb::new(bundled)),
+        (Err(_), Some(system)) => Ok(Adb::new(system)),
+        (Err(e), None) => Err(format!("{} No adb was found on PATH either.", e)),
+    }
+}
+
+fn find_on_path() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(ADB_EXECUTABLE))
+        .find(|candidate| candidate.is_file())
+}
+
+/// `adb version` as comparable numbers: the protocol version from the first
+/// line, then the platform-tools version from the `Version` line, which
+/// releases before 1.0.40 don't print.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct AdbVersion {
+    protocol: Vec<u32>,
+    tools: Vec<u32>,
+}
+
+fn parse_numbers(version: &str) -> Vec<u32> {
+    version
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+fn parse_version(output: &str) -> Option<AdbVersion> {
+    let mut protocol = None;
+    let mut tools = Vec::new();
+    for line in output.lines() {
+        if let Some(rest) = line.strip_prefix("Android Debug Bridge version ") {
+            protocol = Some(parse_numbers(rest.trim()));
+        } else if let Some(rest) = line.strip_prefix("Version ") {
+            tools = parse_numbers(rest.trim());
+        }
+    }
+    Some(AdbVersion {
+        protocol: protocol?,
+        tools,
+    })
+}
+
+/// `None` when the executable doesn't run or doesn't look like adb, which
+/// sorts below every real version.
+fn version(adb: &Path) -> Option<AdbVersion> {
+    let mut child = Command::new(adb)
+        .arg("version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let status = super::wait_with_timeout(&mut child, VERSION_TIMEOUT).ok()??;
+    if !status.success() {
+        return None;
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    parse_version(&output)
+}
+
+fn cache_dir() -> Result<PathBuf, String> {
+    dirs::cache_dir()
+        .map(|dir| {
+            dir.join("purge")
+                .join(format!("adb-{}", env!("CARGO_PKG_VERSION")))
+        })
+        .ok_or_else(|| "No cache directory available for the bundled adb.".to_string())
+}
+
+/// Makes sure every bundled file is in the cache folder with the right
+/// contents and returns the path of the executable.
+fn extract_bundled() -> Result<PathBuf, String> {
+    if ADB_DIR.get_file(ADB_EXECUTABLE).is_none() {
+        return Err("This build does not bundle adb.".to_string());
+    }
+    let dir = cache_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    for file in ADB_DIR.files() {
+        let Some(name) = file.path().file_name() else {
+            continue;
+        };
+        let dest = dir.join(name);
+        if !is_intact(&dest, file.contents()) {
+            write_atomically(&dest, file.contents(), name == ADB_EXECUTABLE)
+                .map_err(|e| format!("Failed to extract {}: {}", dest.display(), e))?;
+        }
+    }
+    Ok(dir.join(ADB_EXECUTABLE))
+}
+
+/// Whether `path` holds exactly `expected`, compared by SHA-256.
+fn is_intact(path: &Path, expected: &[u8]) -> bool {
+    fs::read(path).is_ok_and(|actual| Sha256::digest(actual) == Sha256::digest(expected))
+}
+
+fn write_atomically(path: &Path, contents: &[u8], executable: bool) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+        #[cfg(unix)]
+        if executable {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+        }
+        #[cfg(not(unix))]
+        let _ = executable;
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version_reads_protocol_and_tools_versions() {
+        let output = "Android Debug Bridge version 1.0.41\n\
+            Version 34.0.5-10900879\n\
+            Installed as /usr/bin/adb\n";
+        let version = parse_version(output).unwrap();
+        assert_eq!(version.protocol, [1, 0, 41]);
+        assert_eq!(version.tools, [34, 0, 5]);
+    }
+
+    #[test]
+    fn versions_order_by_protocol_then_tools() {
+        let old = parse_version("Android Debug Bridge version 1.0.39\n").unwrap();
+        let new =
+            parse_version("Android Debug Bridge version 1.0.41\nVersion 33.0.3-8952118\n").unwrap();
+        let newer = parse_version("Android Debug Bridge version 1.0.41\nVersion 35.0.1-11580240\n")
+            .unwrap();
+        assert!(old < new && new < newer);
+        assert!(None < Some(old));
+    }
+
+    #[test]
+    fn parse_version_needs_the_adb_banner() {
+        assert!(parse_version("bash: adb: command not found\n").is_none());
+    }
+}
@@ -0,0 +1,721 @@
+// src/adb/client.rs
+//! A minimal client for the ADB host wire protocol, spoken by the adb server
+//! on `localhost:5037`.
+//!
+//! Every request is a 4-digit hex length followed by the service name. The
+//! server answers `OKAY`, or `FAIL` followed by a hex-length message. Device
+//! services (`shell:`, `reboot:`) first switch the socket to a device with
+//! `host:transport:<serial>`. File transfers use the sync protocol behind
+//! the `sync:` service.
+//!
+//! Every connection has a deadline covering the whole request, so a hung
+//! device fails the call with [`ClientError::TimedOut`] instead of blocking
+//! forever. The one exception is [`track_devices`], which waits for device
+//! changes for as long as the server is up.
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 5037;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Host services answer straight from the server.
+const HOST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Shell and package manager commands on the device.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+/// File transfers, which scale with the size of the APKs.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(600);
+
+// Shell protocol v2 packet ids.
+const SHELL_STDOUT: u8 = 1;
+const SHELL_STDERR: u8 = 2;
+const SHELL_EXIT: u8 = 3;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server could not be reached or the socket failed.
+    Io(io::Error),
+    /// The server answered `FAIL` with this message.
+    Fail(String),
+    /// The server sent something that isn't valid protocol.
+    Protocol(String),
+    /// The request didn't finish before its deadline.
+    TimedOut(Duration),
+}
+
+impl ClientError {
+    /// Whether nothing is listening on the server port.
+    pub fn is_server_down(&self) -> bool {
+        matches!(self, ClientError::Io(e) if matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::TimedOut
+        ))
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "ADB server connection failed: {}", e),
+            ClientError::Fail(msg) => write!(f, "{}", msg),
+            ClientError::Protocol(msg) => write!(f, "ADB protocol error: {}", msg),
+            ClientError::TimedOut(limit) => write!(
+                f,
+                "ADB command timed out after {} seconds; the device may be unresponsive",
+                limit.as_secs()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+/// The server address, honouring `ANDROID_ADB_SERVER_PORT` like adb does.
+pub fn server_addr() -> SocketAddr {
+    let port = std::env::var("ANDROID_ADB_SERVER_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+}
+
+struct Connection {
+    stream: TcpStream,
+    timeout: Duration,
+    /// `None` once the connection is allowed to wait indefinitely.
+    deadline: Option<Instant>,
+}
+
+impl Connection {
+    fn open(timeout: Duration) -> Result<Self, ClientError> {
+        // Connect errors stay `Io` so `is_server_down` can recognise them.
+        let stream =
+            TcpStream::connect_timeout(&server_addr(), CONNECT_TIMEOUT).map_err(ClientError::Io)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            timeout,
+            deadline: Some(Instant::now() + timeout),
+        })
+    }
+
+    /// Limits the next socket operation to the time left before the deadline.
+    fn arm(&self) -> io::Result<()> {
+        let left = match self.deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => {
+                self.stream.set_read_timeout(None)?;
+                return self.stream.set_write_timeout(None);
+            }
+        };
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.set_write_timeout(Some(left))
+    }
+
+    /// Turns socket timeouts into [`ClientError::TimedOut`].
+    fn check<T>(&self, result: io::Result<T>) -> Result<T, ClientError> {
+        result.map_err(|e| match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                ClientError::TimedOut(self.timeout)
+            }
+            _ => ClientError::Io(e),
+        })
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ClientError> {
+        let result = Read::read_exact(self, buf);
+        self.check(result)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), ClientError> {
+        let result = Write::write_all(self, buf);
+        self.check(result)
+    }
+
+    /// Sends one request and waits for its `OKAY`.
+    fn request(&mut self, service: &str) -> Result<(), ClientError> {
+        let message = format!("{:04x}{}", service.len(), service);
+        self.write_all(message.as_bytes())?;
+        self.read_status()
+    }
+
+    fn read_status(&mut self) -> Result<(), ClientError> {
+        let mut status = [0u8; 4];
+        self.read_exact(&mut status)?;
+        match &status {
+            b"OKAY" => Ok(()),
+            b"FAIL" => Err(ClientError::Fail(self.read_string()?)),
+            other => Err(ClientError::Protocol(format!(
+                "unexpected status {:?}",
+                String::from_utf8_lossy(other)
+            ))),
+        }
+    }
+
+    fn read_hex_len(&mut self) -> Result<usize, ClientError> {
+        let mut len = [0u8; 4];
+        self.read_exact(&mut len)?;
+        std::str::from_utf8(&len)
+            .ok()
+            .and_then(|s| usize::from_str_radix(s, 16).ok())
+            .ok_or_else(|| ClientError::Protocol("invalid length prefix".to_string()))
+    }
+
+    fn read_string(&mut self) -> Result<String, ClientError> {
+        let len = self.read_hex_len()?;
+        let mut buf = vec![0u8; len];
+        self.read_exact(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    fn read_to_end(mut self) -> Result<Vec<u8>, ClientError> {
+        let mut buf = Vec::new();
+        let result = Read::read_to_end(&mut self, &mut buf);
+        self.check(result)?;
+        Ok(buf)
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.arm()?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.arm()?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Runs a host service that replies with a single length-prefixed string.
+fn host_query(service: &str, timeout: Duration) -> Result<String, ClientError> {
+    let mut conn = Connection::open(timeout)?;
+    conn.request(service)?;
+    conn.read_string()
+}
+
+fn transport(serial: &str, timeout: Duration) -> Result<Connection, ClientError> {
+    let mut conn = Connection::open(timeout)?;
+    conn.request(&format!("host:transport:{}", serial))?;
+    Ok(conn)
+}
+
+/// The `adb devices -l` listing, without its header line.
+pub fn devices() -> Result<String, ClientError> {
+    host_query("host:devices-l", HOST_TIMEOUT)
+}
+
+/// A `host:track-devices-l` subscription. The server sends the device
+/// listing right away, then again whenever a device attaches, detaches or
+/// changes state.
+pub struct DeviceTracker {
+    conn: Connection,
+}
+
+impl DeviceTracker {
+    /// Blocks until the next listing, in the `adb devices -l` format. Fails
+    /// once the server goes away.
+    pub fn next_listing(&mut self) -> Result<String, ClientError> {
+        self.conn.read_string()
+    }
+}
+
+pub fn track_devices() -> Result<DeviceTracker, ClientError> {
+    let mut conn = Connection::open(HOST_TIMEOUT)?;
+    conn.request("host:track-devices-l")?;
+    conn.deadline = None;
+    Ok(DeviceTracker { conn })
+}
+
+/// The running server's protocol version, the last part of its
+/// `adb version`: 41 for 1.0.41.
+pub fn server_version() -> Result<u32, ClientError> {
+    let reply = host_query("host:version", HOST_TIMEOUT)?;
+    u32::from_str_radix(reply.trim(), 16)
+        .map_err(|_| ClientError::Protocol(format!("invalid server version {:?}", reply)))
+}
+
+/// Asks the server to exit, like `adb kill-server`.
+pub fn kill_server() -> Result<(), ClientError> {
+    let mut conn = Connection::open(HOST_TIMEOUT)?;
+    conn.request("host:kill")
+}
+
+pub fn features(serial: &str) -> Result<Vec<String>, ClientError> {
+    Ok(
+        host_query(&format!("host-serial:{}:features", serial), HOST_TIMEOUT)?
+            .split(',')
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .collect(),
+    )
+}
+
+/// Whether the device speaks shell protocol v2. Only answers are cached: a
+/// failed query, e.g. while the device is still unauthorized, is retried on
+/// the next call.
+fn supports_shell_v2(serial: &str) -> bool {
+    static CACHE: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(&known) = cache.lock().unwrap().get(serial) {
+        return known;
+    }
+    match features(serial) {
+        Ok(features) => {
+            let supported = features.iter().any(|f| f == "shell_v2");
+            cache.lock().unwrap().insert(serial.to_string(), supported);
+            supported
+        }
+        Err(_) => false,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ShellOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Only known when the device speaks shell protocol v2.
+    pub exit_code: Option<u8>,
+}
+
+impl ShellOutput {
+    /// Without shell v2 there is no exit code, so this only means nothing
+    /// reported a failure; callers that can should check the output too.
+    pub fn succeeded(&self) -> bool {
+        self.exit_code.is_none_or(|code| code == 0)
+    }
+}
+
+/// Runs `command` on the device. Uses shell protocol v2 when available so
+/// stdout, stderr and the exit code come back separately.
+pub fn shell(serial: &str, command: &str) -> Result<ShellOutput, ClientError> {
+    if supports_shell_v2(serial) {
+        let mut conn = transport(serial, COMMAND_TIMEOUT)?;
+        conn.request(&format!("shell,v2,raw:{}", command))?;
+        read_shell_v2(&mut conn)
+    } else {
+        let mut conn = transport(serial, COMMAND_TIMEOUT)?;
+        conn.request(&format!("shell:{}", command))?;
+        Ok(ShellOutput {
+            stdout: String::from_utf8_lossy(&conn.read_to_end()?).into_owned(),
+            ..Default::default()
+        })
+    }
+}
+
+fn read_shell_v2(conn: &mut Connection) -> Result<ShellOutput, ClientError> {
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut exit_code = None;
+    let mut header = [0u8; 5];
+    loop {
+        match conn.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(ClientError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let mut payload = vec![0u8; len];
+        conn.read_exact(&mut payload)?;
+        match header[0] {
+            SHELL_STDOUT => stdout.extend_from_slice(&payload),
+            SHELL_STDERR => stderr.extend_from_slice(&payload),
+            SHELL_EXIT => {
+                exit_code = payload.first().copied();
+                break;
+            }
+            _ => {}
+        }
+    }
+    Ok(ShellOutput {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        exit_code,
+    })
+}
+
+/// Runs `command` through the `exec:` service, which passes bytes through
+/// untouched in both directions. `input` is streamed to the command's stdin
+/// before its output is read.
+pub fn exec(
+    serial: &str,
+    command: &str,
+    input: Option<&mut dyn Read>,
+) -> Result<String, ClientError> {
+    let timeout = if input.is_some() {
+        TRANSFER_TIMEOUT
+    } else {
+        COMMAND_TIMEOUT
+    };
+    let mut conn = transport(serial, timeout)?;
+    conn.request(&format!("exec:{}", command))?;
+    if let Some(input) = input {
+        let result = io::copy(input, &mut conn);
+        conn.check(result)?;
+    }
+    Ok(String::from_utf8_lossy(&conn.read_to_end()?).into_owned())
+}
+
+/// Copies `remote` from the device into `out` with the sync protocol and
+/// returns the number of bytes written.
+pub fn pull(serial: &str, remote: &str, out: &mut impl Write) -> Result<u64, ClientError> {
+    let mut conn = transport(serial, TRANSFER_TIMEOUT)?;
+    conn.request("sync:")?;
+    let stream = &mut conn;
+    stream.write_all(b"RECV")?;
+    stream.write_all(&(remote.len() as u32).to_le_bytes())?;
+    stream.write_all(remote.as_bytes())?;
+
+    let mut total = 0u64;
+    let mut header = [0u8; 8];
+    loop {
+        stream.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        match &header[..4] {
+            b"DATA" => {
+                let mut chunk = vec![0u8; len];
+                stream.read_exact(&mut chunk)?;
+                out.write_all(&chunk)?;
+                total += len as u64;
+            }
+            b"DONE" => break,
+            b"FAIL" => {
+                let mut message = vec![0u8; len];
+                stream.read_exact(&mut message)?;
+                return Err(ClientError::Fail(format!(
+                    "Failed to pull {}: {}",
+                    remote,
+                    String::from_utf8_lossy(&message)
+                )));
+            }
+            other => {
+                return Err(ClientError::Protocol(format!(
+                    "unexpected sync response {:?}",
+                    String::from_utf8_lossy(other)
+                )))
+            }
+        }
+    }
+    stream.write_all(b"QUIT\0\0\0\0")?;
+    Ok(total)
+}
+
+/// Pairs with a device's wireless debugging pairing service, like
+/// `adb pair`. The reply says whether it worked.
+pub fn pair(address: &str, code: &str) -> Result<String, ClientError> {
+    host_query(&format!("host:pair:{}:{}", code, address), COMMAND_TIMEOUT)
+}
+
+/// Asks the server to connect to a device over TCP/IP, like `adb connect`.
+/// The server answers `OKAY` even when connecting failed; the reply says
+/// which.
+pub fn connect(address: &str) -> Result<String, ClientError> {
+    host_query(&format!("host:connect:{}", address), COMMAND_TIMEOUT)
+}
+
+/// Drops a TCP/IP device, or every one of them when `address` is empty.
+pub fn disconnect(address: &str) -> Result<String, ClientError> {
+    host_query(&format!("host:disconnect:{}", address), HOST_TIMEOUT)
+}
+
+/// Restarts adbd on the device listening on `port`, like `adb tcpip`.
+pub fn tcpip(serial: &str, port: u16) -> Result<String, ClientError> {
+    let mut conn = transport(serial, COMMAND_TIMEOUT)?;
+    conn.request(&format!("tcpip:{}", port))?;
+    Ok(String::from_utf8_lossy(&conn.read_to_end()?).into_owned())
+}
+
+pub fn reboot(serial: &str) -> Result<(), ClientError> {
+    let mut conn = transport(serial, COMMAND_TIMEOUT)?;
+    conn.request("reboot:")?;
+    // The device drops the connection as it goes down; anything read
+    // before that is irrelevant.
+    let _ = conn.read_to_end();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::MutexGuard;
+    use std::thread;
+
+    /// `ANDROID_ADB_SERVER_PORT` is process-wide, so tests that point it at
+    /// their own server take turns.
+    static SERVER_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_server() -> MutexGuard<'static, ()> {
+        SERVER_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn point_client_at(port: u16) {
+        std::env::set_var("ANDROID_ADB_SERVER_PORT", port.to_string());
+    }
+
+    /// Points the client at a loopback server that hands its next
+    /// `connections` connections, numbered from 0, to `handle`.
+    fn fake_server(
+        connections: usize,
+        handle: impl Fn(usize, &mut TcpStream) + Send + 'static,
+    ) -> MutexGuard<'static, ()> {
+        let guard = lock_server();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        point_client_at(listener.local_addr().unwrap().port());
+        thread::spawn(move || {
+            for i in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                handle(i, &mut stream);
+            }
+        });
+        guard
+    }
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let len = usize::from_str_radix(std::str::from_utf8(&len).unwrap(), 16).unwrap();
+        let mut service = vec![0u8; len];
+        stream.read_exact(&mut service).unwrap();
+        String::from_utf8(service).unwrap()
+    }
+
+    /// `status` followed by a length-prefixed `message`.
+    fn reply(stream: &mut TcpStream, status: &str, message: &str) {
+        write!(stream, "{}{:04x}{}", status, message.len(), message).unwrap();
+    }
+
+    fn shell_packet(id: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = vec![id];
+        packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    fn sync_packet(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut packet = id.to_vec();
+        packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    #[test]
+    fn host_query_sends_length_prefixed_service_and_reads_reply() {
+        let _server = fake_server(1, |_, stream| {
+            assert_eq!(read_request(stream), "host:devices-l");
+            reply(stream, "OKAY", "ABC123 device usb:1-1\n");
+        });
+        assert_eq!(devices().unwrap(), "ABC123 device usb:1-1\n");
+    }
+
+    #[test]
+    fn fail_reply_carries_the_server_message() {
+        let _server = fake_server(1, |_, stream| {
+            read_request(stream);
+            reply(stream, "FAIL", "device 'XYZ' not found");
+        });
+        match disconnect("XYZ") {
+            Err(ClientError::Fail(message)) => assert_eq!(message, "device 'XYZ' not found"),
+            other => panic!("expected Fail, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_a_protocol_error() {
+        let _server = fake_server(1, |_, stream| {
+            read_request(stream);
+            stream.write_all(b"WHAT").unwrap();
+        });
+        assert!(matches!(devices(), Err(ClientError::Protocol(_))));
+    }
+
+    #[test]
+    fn invalid_length_prefix_is_a_protocol_error() {
+        let _server = fake_server(1, |_, stream| {
+            read_request(stream);
+            stream.write_all(b"OKAYzzzz").unwrap();
+        });
+        assert!(matches!(devices(), Err(ClientError::Protocol(_))));
+    }
+
+    #[test]
+    fn refused_tracking_is_reported_without_a_listing() {
+        let _server = fake_server(1, |_, stream| {
+            assert_eq!(read_request(stream), "host:track-devices-l");
+            reply(stream, "FAIL", "unknown host service");
+        });
+        let mut called = false;
+        let tracked = super::super::track_devices(&mut |_| {
+            called = true;
+            false
+        });
+        assert_eq!(tracked, Ok(false));
+        assert!(!called);
+    }
+
+    #[test]
+    fn server_version_is_hex() {
+        let _server = fake_server(1, |_, stream| {
+            assert_eq!(read_request(stream), "host:version");
+            reply(stream, "OKAY", "0029");
+        });
+        assert_eq!(server_version().unwrap(), 41);
+    }
+
+    #[test]
+    fn shell_v2_separates_streams_and_exit_code() {
+        let _server = fake_server(2, |i, stream| {
+            if i == 0 {
+                assert_eq!(read_request(stream), "host-serial:V2DEVICE:features");
+                reply(stream, "OKAY", "cmd,shell_v2");
+                return;
+            }
+            assert_eq!(read_request(stream), "host:transport:V2DEVICE");
+            stream.write_all(b"OKAY").unwrap();
+            assert_eq!(read_request(stream), "shell,v2,raw:pm uninstall x");
+            stream.write_all(b"OKAY").unwrap();
+            stream
+                .write_all(&shell_packet(SHELL_STDOUT, b"Fail"))
+                .unwrap();
+            stream
+                .write_all(&shell_packet(SHELL_STDOUT, b"ure\n"))
+                .unwrap();
+            stream
+                .write_all(&shell_packet(SHELL_STDERR, b"oops\n"))
+                .unwrap();
+            stream.write_all(&shell_packet(SHELL_EXIT, &[1])).unwrap();
+        });
+        let output = shell("V2DEVICE", "pm uninstall x").unwrap();
+        assert_eq!(output.stdout, "Failure\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert_eq!(output.exit_code, Some(1));
+        assert!(!output.succeeded());
+    }
+
+    #[test]
+    fn shell_without_v2_reads_raw_output() {
+        let _server = fake_server(2, |i, stream| {
+            if i == 0 {
+                read_request(stream);
+                reply(stream, "OKAY", "cmd");
+                return;
+            }
+            read_request(stream);
+            stream.write_all(b"OKAY").unwrap();
+            assert_eq!(read_request(stream), "shell:getprop ro.product.model");
+            stream.write_all(b"OKAYPixel 7\n").unwrap();
+        });
+        let output = shell("V1DEVICE", "getprop ro.product.model").unwrap();
+        assert_eq!(output.stdout, "Pixel 7\n");
+        assert_eq!(output.exit_code, None);
+        assert!(output.succeeded());
+    }
+
+    #[test]
+    fn failed_feature_query_is_not_cached() {
+        let _server = fake_server(2, |i, stream| {
+            read_request(stream);
+            if i == 0 {
+                reply(stream, "FAIL", "device unauthorized");
+            } else {
+                reply(stream, "OKAY", "shell_v2");
+            }
+        });
+        assert!(!supports_shell_v2("LATEDEVICE"));
+        assert!(supports_shell_v2("LATEDEVICE"));
+    }
+
+    /// Reads the sync `RECV` request and returns the requested path.
+    fn read_recv(stream: &mut TcpStream) -> String {
+        assert_eq!(read_request(stream), "host:transport:SYNCDEVICE");
+        stream.write_all(b"OKAY").unwrap();
+        assert_eq!(read_request(stream), "sync:");
+        stream.write_all(b"OKAY").unwrap();
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(&header[..4], b"RECV");
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let mut path = vec![0u8; len];
+        stream.read_exact(&mut path).unwrap();
+        String::from_utf8(path).unwrap()
+    }
+
+    #[test]
+    fn pull_collects_data_chunks_until_done() {
+        let _server = fake_server(1, |_, stream| {
+            assert_eq!(read_recv(stream), "/data/app/base.apk");
+            stream
+                .write_all(&sync_packet(b"DATA", b"PK\x03\x04"))
+                .unwrap();
+            stream.write_all(&sync_packet(b"DATA", b"rest")).unwrap();
+            stream.write_all(&sync_packet(b"DONE", b"")).unwrap();
+            let mut quit = [0u8; 8];
+            stream.read_exact(&mut quit).unwrap();
+            assert_eq!(&quit[..4], b"QUIT");
+        });
+        let mut out = Vec::new();
+        let total = pull("SYNCDEVICE", "/data/app/base.apk", &mut out).unwrap();
+        assert_eq!(total, 8);
+        assert_eq!(out, b"PK\x03\x04rest");
+    }
+
+    #[test]
+    fn pull_reports_sync_failure() {
+        let _server = fake_server(1, |_, stream| {
+            read_recv(stream);
+            stream
+                .write_all(&sync_packet(b"FAIL", b"No such file or directory"))
+                .unwrap();
+        });
+        match pull("SYNCDEVICE", "/missing.apk", &mut Vec::new()) {
+            Err(ClientError::Fail(message)) => {
+                assert!(message.contains("No such file or directory"))
+            }
+            other => panic!("expected Fail, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn silent_server_times_out() {
+        let _server = fake_server(1, |_, stream| {
+            read_request(stream);
+            thread::sleep(Duration::from_millis(500));
+        });
+        let limit = Duration::from_millis(100);
+        let mut conn = Connection::open(limit).unwrap();
+        match conn.request("host:version") {
+            Err(ClientError::TimedOut(reported)) => assert_eq!(reported, limit),
+            other => panic!("expected TimedOut, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refused_connection_means_server_down() {
+        let _guard = lock_server();
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        point_client_at(port);
+        assert!(devices().unwrap_err().is_server_down());
+    }
+}
@@ -0,0 +1,645 @@
+// src/adb/fake.rs
+//! An in-memory stand-in for real devices, loaded from a JSON fixture:
+//!
+//! ```json
+//! {
+//!   "devices": [
+//!     {
+//!       "serial": "FAKE0001",
+//!       "model": "Pixel 7",
+//!       "packages": {
+//!         "com.google.android.youtube": "enabled",
+//!         "com.facebook.system": "removed"
+//!       },
+//!       "third_party": ["com.example.app"],
+//!       "failures": { "com.android.vending": "DELETE_FAILED_INTERNAL_ERROR" },
+//!       "users": [
+//!         {
+//!           "id": 10,
+//!           "name": "Work profile",
+//!           "work_profile": true,
+//!           "packages": { "com.google.android.youtube": "removed" }
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `packages` belong to the primary user; `users` adds further users and
+//! profiles with their own package states.
+//!
+//! Connecting to any address over TCP/IP adds a copy of the first
+//! authorized device under that address.
+//!
+//! Operations change the in-memory state only; the fixture file is never
+//! written back.
+use super::{
+    is_valid_package_name, normalize_endpoint, Device, DeviceBackend, DeviceState, DeviceUser,
+    InstalledPackage, PackageMetadata, PmOutcome, PurgeAction, RestrictedPackages, ServerStatus,
+    DEFAULT_TCPIP_PORT, PRIMARY_USER,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FakeState {
+    Enabled,
+    Disabled,
+    Suspended,
+    Hidden,
+    Removed,
+}
+
+impl FakeState {
+    fn applied_by(action: PurgeAction) -> Self {
+        match action {
+            PurgeAction::Uninstall => FakeState::Removed,
+            PurgeAction::Disable => FakeState::Disabled,
+            PurgeAction::Suspend => FakeState::Suspended,
+            PurgeAction::Hide => FakeState::Hidden,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FakeState::Enabled => "enabled",
+            FakeState::Disabled => "disabled",
+            FakeState::Suspended => "suspended",
+            FakeState::Hidden => "hidden",
+            FakeState::Removed => "removed",
+        }
+    }
+
+    /// Whether plain `pm list packages` would show the package. Like on a
+    /// real device, hidden packages only show up with `-u`.
+    fn is_listed(self) -> bool {
+        !matches!(self, FakeState::Hidden | FakeState::Removed)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FakeDevice {
+    serial: String,
+    #[serde(default = "default_state")]
+    state: String,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    packages: BTreeMap<String, FakeState>,
+    /// Packages installed by the user; everything else is a system app.
+    #[serde(default)]
+    third_party: BTreeSet<String>,
+    /// Packages whose operations always fail, with the reason to report.
+    #[serde(default)]
+    failures: BTreeMap<String, String>,
+    /// Users besides the primary one.
+    #[serde(default)]
+    users: Vec<FakeUser>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FakeUser {
+    id: u32,
+    name: String,
+    #[serde(default)]
+    work_profile: bool,
+    #[serde(default)]
+    packages: BTreeMap<String, FakeState>,
+}
+
+impl FakeDevice {
+    fn packages(&self, user: u32) -> Result<&BTreeMap<String, FakeState>, String> {
+        if user == PRIMARY_USER {
+            return Ok(&self.packages);
+        }
+        self.users
+            .iter()
+            .find(|u| u.id == user)
+            .map(|u| &u.packages)
+            .ok_or_else(|| format!("User {} does not exist.", user))
+    }
+
+    fn packages_mut(&mut self, user: u32) -> Result<&mut BTreeMap<String, FakeState>, String> {
+        if user == PRIMARY_USER {
+            return Ok(&mut self.packages);
+        }
+        self.users
+            .iter_mut()
+            .find(|u| u.id == user)
+            .map(|u| &mut u.packages)
+            .ok_or_else(|| format!("User {} does not exist.", user))
+    }
+
+    fn is_system(&self, id: &str) -> bool {
+        !self.third_party.contains(id)
+    }
+
+    fn apk_path(&self, id: &str) -> String {
+        if self.is_system(id) {
+            format!("/system/app/{}/{}.apk", id, id)
+        } else {
+            format!("/data/app/{}-1/base.apk", id)
+        }
+    }
+}
+
+fn default_state() -> String {
+    "device".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    devices: Vec<FakeDevice>,
+}
+
+pub struct FakeBackend {
+    devices: Mutex<Vec<FakeDevice>>,
+}
+
+impl FakeBackend {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read fixture {}: {}", path.display(), e))?;
+        let fixture: Fixture = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+        Ok(Self {
+            devices: Mutex::new(fixture.devices),
+        })
+    }
+
+    fn with_device<T>(
+        &self,
+        serial: &str,
+        f: impl FnOnce(&mut FakeDevice) -> T,
+    ) -> Result<T, String> {
+        let mut devices = self.devices.lock().unwrap();
+        match devices.iter_mut().find(|d| d.serial == serial) {
+            Some(device) if device.state == "device" => Ok(f(device)),
+            Some(device) => Err(format!("Device {} is {}.", serial, device.state)),
+            None => Err(format!("Device {} is no longer connected.", serial)),
+        }
+    }
+
+    fn packages_where(
+        &self,
+        serial: &str,
+        user: u32,
+        keep: impl Fn(FakeState) -> bool,
+    ) -> Result<Vec<String>, String> {
+        self.with_device(serial, |device| {
+            Ok(device
+                .packages(user)?
+                .iter()
+                .filter(|(_, state)| keep(**state))
+                .map(|(id, _)| id.clone())
+                .collect())
+        })?
+    }
+
+    fn run(
+        &self,
+        serial: &str,
+        user: u32,
+        package: &str,
+        apply: impl FnOnce(&mut FakeState) -> PmOutcome,
+    ) -> PmOutcome {
+        if !is_valid_package_name(package) {
+            return PmOutcome::Failure(format!("Invalid package name '{}'", package));
+        }
+        let outcome = self.with_device(serial, |device| {
+            if let Some(reason) = device.failures.get(package) {
+                return PmOutcome::Failure(reason.clone());
+            }
+            match device.packages_mut(user).map(|p| p.get_mut(package)) {
+                Ok(Some(state)) => apply(state),
+                Ok(None) => PmOutcome::Failure(format!("Package {} doesn't exist", package)),
+                Err(e) => PmOutcome::Failure(e),
+            }
+        });
+        outcome.unwrap_or_else(PmOutcome::Failure)
+    }
+}
+
+impl DeviceBackend for FakeBackend {
+    fn list_devices(&self) -> Result<Vec<Device>, String> {
+        Ok(self
+            .devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|d| Device {
+                serial: d.serial.clone(),
+                state: DeviceState::parse(&d.state),
+                model: d.model.as_ref().map(|m| m.replace(' ', "_")),
+                transport_id: None,
+            })
+            .collect())
+    }
+
+    fn device_model(&self, serial: &str) -> Result<String, String> {
+        self.with_device(serial, |device| device.model.clone())?
+            .ok_or_else(|| "Device model name is empty.".to_string())
+    }
+
+    fn list_users(&self, serial: &str) -> Result<Vec<DeviceUser>, String> {
+        self.with_device(serial, |device| {
+            let owner = DeviceUser {
+                id: PRIMARY_USER,
+                name: "Owner".to_string(),
+                flags: 0xc13,
+                running: true,
+            };
+            let others = device.users.iter().map(|u| DeviceUser {
+                id: u.id,
+                name: u.name.clone(),
+                flags: if u.work_profile { 0x1030 } else { 0x410 },
+                running: true,
+            });
+            std::iter::once(owner).chain(others).collect()
+        })
+    }
+
+    fn list_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String> {
+        self.packages_where(serial, user, FakeState::is_listed)
+    }
+
+    fn list_removed_packages(
+        &self,
+        serial: &str,
+        user: u32,
+        installed: &[String],
+    ) -> Result<Vec<String>, String> {
+        Ok(self
+            .packages_where(serial, user, |_| true)?
+            .into_iter()
+            .filter(|pkg| !installed.contains(pkg))
+            .collect())
+    }
+
+    fn list_disabled_packages(&self, serial: &str, user: u32) -> Result<Vec<String>, String> {
+        self.packages_where(serial, user, |state| state == FakeState::Disabled)
+    }
+
+    fn list_restricted_packages(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<RestrictedPackages, String> {
+        Ok(RestrictedPackages {
+            hidden: self.packages_where(serial, user, |state| state == FakeState::Hidden)?,
+            suspended: self.packages_where(serial, user, |state| state == FakeState::Suspended)?,
+        })
+    }
+
+    fn list_package_details(
+        &self,
+        serial: &str,
+        user: u32,
+    ) -> Result<Vec<InstalledPackage>, String> {
+        self.with_device(serial, |device| {
+            Ok(device
+                .packages(user)?
+                .iter()
+                .filter(|(_, state)| state.is_listed())
+                .map(|(id, _)| InstalledPackage {
+                    id: id.clone(),
+                    apk_path: device.apk_path(id),
+                    installer: None,
+                    system: device.is_system(id),
+                })
+                .collect())
+        })?
+    }
+
+    fn package_metadata(
+        &self,
+        serial: &str,
+        user: u32,
+        package: &str,
+    ) -> Result<PackageMetadata, String> {
+        self.with_device(serial, |device| {
+            let state = device
+                .packages(user)?
+                .get(package)
+                .ok_or_else(|| format!("{} is not known to the package manager.", package))?;
+            Ok(PackageMetadata {
+                version_name: Some("1.0".to_string()),
+                version_code: Some("1".to_string()),
+                code_path: Some(device.apk_path(package)),
+                enabled_state: Some(
+                    match state {
+                        FakeState::Disabled => "Disabled by user",
+                        _ => "Default",
+                    }
+                    .to_string(),
+                ),
+                system: device.is_system(package),
+                ..Default::default()
+            })
+        })?
+    }
+
+    fn purge(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+        self.run(serial, user, package, |state| {
+            if *state == FakeState::Removed {
+                return PmOutcome::NotInstalled;
+            }
+            *state = FakeState::applied_by(action);
+            PmOutcome::Success
+        })
+    }
+
+    fn revert(&self, serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+        // Stricter than a device, which shrugs at most mismatched reverts,
+        // so callers picking the wrong action show up here.
+        self.run(serial, user, package, |state| {
+            let expected = FakeState::applied_by(action);
+            if *state == expected {
+                *state = FakeState::Enabled;
+                PmOutcome::Success
+            } else if *state == FakeState::Removed {
+                PmOutcome::NotInstalled
+            } else {
+                PmOutcome::Failure(format!(
+                    "Package {} is {}, not {}",
+                    package,
+                    state.label(),
+                    expected.label()
+                ))
+            }
+        })
+    }
+
+    fn reboot(&self, _serial: &str) {}
+
+    /// There is no server; report one that matches, as adb 1.0.41 would.
+    fn server_status(&self) -> Result<ServerStatus, String> {
+        Ok(ServerStatus {
+            server_version: Some(41),
+            adb_version: Some(41),
+            adb_path: None,
+        })
+    }
+
+    fn restart_server(&self) -> Result<ServerStatus, String> {
+        self.server_status()
+    }
+
+    fn pair(&self, address: &str, code: &str) -> Result<String, String> {
+        let address = normalize_endpoint(address, None)?;
+        if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+            Ok(format!("Successfully paired to {}", address))
+        } else {
+            Err("Failed: Wrong password or connection was dropped.".to_string())
+        }
+    }
+
+    fn connect(&self, address: &str) -> Result<String, String> {
+        let address = normalize_endpoint(address, Some(DEFAULT_TCPIP_PORT))?;
+        let mut devices = self.devices.lock().unwrap();
+        if devices.iter().any(|d| d.serial == address) {
+            return Ok(address);
+        }
+        let mut device = devices
+            .iter()
+            .find(|d| d.state == "device")
+            .cloned()
+            .ok_or_else(|| format!("failed to connect to {}", address))?;
+        device.serial = address.clone();
+        devices.push(device);
+        Ok(address)
+    }
+
+    fn disconnect(&self, address: Option<&str>) -> Result<(), String> {
+        let address = address
+            .map(|a| normalize_endpoint(a, Some(DEFAULT_TCPIP_PORT)))
+            .transpose()?;
+        let mut devices = self.devices.lock().unwrap();
+        let before = devices.len();
+        devices.retain(|d| match &address {
+            Some(address) => d.serial != *address,
+            None => !d.serial.contains(':'),
+        });
+        match address {
+            Some(address) if devices.len() == before => {
+                Err(format!("no such device '{}'", address))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn enable_tcpip(&self, serial: &str, port: u16) -> Result<Option<String>, String> {
+        self.with_device(serial, |_| Some(format!("192.168.0.100:{}", port)))
+    }
+
+    fn apk_paths(&self, serial: &str, user: u32, package: &str) -> Result<Vec<String>, String> {
+        self.with_device(serial, |device| {
+            Ok(match device.packages(user)?.get(package) {
+                Some(state) if *state != FakeState::Removed || device.is_system(package) => {
+                    vec![device.apk_path(package)]
+                }
+                _ => Vec::new(),
+            })
+        })?
+    }
+
+    fn pull_file(&self, serial: &str, remote: &str, local: &Path) -> Result<(), String> {
+        self.with_device(serial, |_| ())?;
+        fs::write(local, format!("fake APK pulled from {}\n", remote))
+            .map_err(|e| format!("Failed to create {}: {}", local.display(), e))
+    }
+
+    fn install_apks(&self, serial: &str, user: u32, package: &str, apks: &[PathBuf]) -> PmOutcome {
+        if apks.is_empty() {
+            return PmOutcome::Failure("INSTALL_FAILED_INVALID_APK".to_string());
+        }
+        let installed = self.with_device(serial, |device| {
+            device.packages_mut(user).map(|packages| {
+                packages.insert(package.to_string(), FakeState::Enabled);
+            })
+        });
+        match installed {
+            Ok(Ok(())) => PmOutcome::Success,
+            Ok(Err(e)) | Err(e) => PmOutcome::Failure(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::PackageState;
+
+    fn fixture() -> FakeBackend {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/fake_device.json");
+        FakeBackend::load(&path).unwrap()
+    }
+
+    #[test]
+    fn load_device_reads_the_primary_user() {
+        let snapshot = fixture().load_device("FAKE0001", PRIMARY_USER).unwrap();
+        assert_eq!(snapshot.model, "Pixel 7");
+        assert!(snapshot
+            .installed
+            .contains(&"com.google.android.youtube".to_string()));
+        assert!(snapshot
+            .installed
+            .contains(&"com.facebook.system".to_string()));
+        assert_eq!(snapshot.removed, ["com.facebook.appmanager"]);
+        assert_eq!(snapshot.hidden, ["com.google.android.apps.tachyon"]);
+        assert_eq!(snapshot.disabled, ["com.facebook.system"]);
+        assert_eq!(snapshot.suspended, ["com.google.android.apps.docs"]);
+        assert_eq!(
+            snapshot.state_of("com.google.android.apps.docs"),
+            Some(PackageState::Suspended)
+        );
+        let sideloaded = snapshot
+            .details
+            .iter()
+            .find(|d| d.id == "com.example.sideloaded")
+            .unwrap();
+        assert!(!sideloaded.system);
+    }
+
+    #[test]
+    fn unauthorized_device_cannot_be_loaded() {
+        let error = fixture().load_device("FAKE0002", PRIMARY_USER).unwrap_err();
+        assert!(error.contains("unauthorized"), "{}", error);
+    }
+
+    #[test]
+    fn purge_then_revert_round_trips() {
+        let backend = fixture();
+        let package = "com.google.android.youtube";
+        let outcome = backend.purge("FAKE0001", PRIMARY_USER, package, PurgeAction::Uninstall);
+        assert_eq!(outcome, PmOutcome::Success);
+        let snapshot = backend.load_device("FAKE0001", PRIMARY_USER).unwrap();
+        assert!(!snapshot.installed.contains(&package.to_string()));
+        assert!(snapshot.removed.contains(&package.to_string()));
+
+        let outcome = backend.revert("FAKE0001", PRIMARY_USER, package, PurgeAction::Uninstall);
+        assert_eq!(outcome, PmOutcome::Success);
+        let snapshot = backend.load_device("FAKE0001", PRIMARY_USER).unwrap();
+        assert!(snapshot.installed.contains(&package.to_string()));
+    }
+
+    #[test]
+    fn hidden_package_comes_back_with_unhide() {
+        let backend = fixture();
+        let package = "com.google.android.apps.tachyon";
+        let snapshot = backend.load_device("FAKE0001", PRIMARY_USER).unwrap();
+        assert_eq!(snapshot.state_of(package), Some(PackageState::Hidden));
+
+        let action = snapshot.state_of(package).unwrap().purge_action().unwrap();
+        assert_eq!(action, PurgeAction::Hide);
+        let outcome = backend.revert("FAKE0001", PRIMARY_USER, package, action);
+        assert_eq!(outcome, PmOutcome::Success);
+        let snapshot = backend.load_device("FAKE0001", PRIMARY_USER).unwrap();
+        assert_eq!(snapshot.state_of(package), Some(PackageState::Enabled));
+    }
+
+    #[test]
+    fn revert_with_the_wrong_action_fails() {
+        let backend = fixture();
+        let package = "com.google.android.apps.tachyon";
+        let outcome = backend.revert("FAKE0001", PRIMARY_USER, package, PurgeAction::Uninstall);
+        assert_eq!(
+            outcome,
+            PmOutcome::Failure(format!("Package {} is hidden, not removed", package))
+        );
+        let hidden = backend
+            .list_restricted_packages("FAKE0001", PRIMARY_USER)
+            .unwrap()
+            .hidden;
+        assert!(hidden.contains(&package.to_string()));
+    }
+
+    #[test]
+    fn disable_shows_up_as_disabled() {
+        let backend = fixture();
+        let package = "com.google.android.gm";
+        backend.purge("FAKE0001", PRIMARY_USER, package, PurgeAction::Disable);
+        let disabled = backend
+            .list_disabled_packages("FAKE0001", PRIMARY_USER)
+            .unwrap();
+        assert!(disabled.contains(&package.to_string()));
+    }
+
+    #[test]
+    fn fixture_failure_is_reported_and_leaves_the_package() {
+        let backend = fixture();
+        let outcome = backend.purge(
+            "FAKE0001",
+            PRIMARY_USER,
+            "com.android.vending",
+            PurgeAction::Uninstall,
+        );
+        assert_eq!(
+            outcome,
+            PmOutcome::Failure("DELETE_FAILED_INTERNAL_ERROR".to_string())
+        );
+        let installed = backend.list_packages("FAKE0001", PRIMARY_USER).unwrap();
+        assert!(installed.contains(&"com.android.vending".to_string()));
+    }
+
+    #[test]
+    fn purging_a_removed_package_is_not_installed() {
+        let outcome = fixture().purge(
+            "FAKE0001",
+            PRIMARY_USER,
+            "com.facebook.appmanager",
+            PurgeAction::Uninstall,
+        );
+        assert_eq!(outcome, PmOutcome::NotInstalled);
+    }
+
+    #[test]
+    fn work_profile_has_its_own_packages() {
+        let backend = fixture();
+        let users = backend.list_users("FAKE0001").unwrap();
+        let ids: Vec<u32> = users.iter().map(|u| u.id).collect();
+        assert_eq!(ids, [PRIMARY_USER, 10]);
+        assert!(users[1].is_work_profile());
+
+        let snapshot = backend.load_device("FAKE0001", 10).unwrap();
+        assert!(!snapshot
+            .installed
+            .contains(&"com.google.android.youtube".to_string()));
+        assert!(snapshot
+            .removed
+            .contains(&"com.google.android.youtube".to_string()));
+        assert_eq!(
+            snapshot.installed_for.get("com.google.android.gm"),
+            Some(&vec![PRIMARY_USER, 10])
+        );
+        assert_eq!(
+            snapshot.installed_for.get("com.google.android.youtube"),
+            Some(&vec![PRIMARY_USER])
+        );
+    }
+
+    #[test]
+    fn purge_for_one_user_leaves_the_other() {
+        let backend = fixture();
+        backend.purge("FAKE0001", 10, "com.android.chrome", PurgeAction::Uninstall);
+        assert!(!backend
+            .list_packages("FAKE0001", 10)
+            .unwrap()
+            .contains(&"com.android.chrome".to_string()));
+        assert!(backend
+            .list_packages("FAKE0001", PRIMARY_USER)
+            .unwrap()
+            .contains(&"com.android.chrome".to_string()));
+    }
+
+    #[test]
+    fn unknown_user_is_rejected() {
+        let error = fixture().resolve_user("FAKE0001", Some(11)).unwrap_err();
+        assert!(error.contains("Work profile (10)"), "{}", error);
+    }
+}
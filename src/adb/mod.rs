@@ -0,0 +1,1443 @@
+// src/adb/mod.rs
+mod backend;
+mod binary;
+mod client;
+mod fake;
+
+pub use backend::{AdbBackend, DeviceBackend};
+pub use fake::FakeBackend;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// A device's state as `adb devices` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(into = "String")]
+pub enum DeviceState {
+    /// Ready for use.
+    Device,
+    Unauthorized,
+    Authorizing,
+    Offline,
+    Connecting,
+    /// The host can't open the USB device; carries adb's explanation.
+    NoPermissions(String),
+    Recovery,
+    Sideload,
+    Bootloader,
+    Rescue,
+    Other(String),
+}
+
+impl DeviceState {
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "device" => DeviceState::Device,
+            "unauthorized" => DeviceState::Unauthorized,
+            "authorizing" => DeviceState::Authorizing,
+            "offline" => DeviceState::Offline,
+            "connecting" => DeviceState::Connecting,
+            "recovery" => DeviceState::Recovery,
+            "sideload" => DeviceState::Sideload,
+            "bootloader" => DeviceState::Bootloader,
+            "rescue" => DeviceState::Rescue,
+            other => match other.strip_prefix("no permissions") {
+                Some(detail) => DeviceState::NoPermissions(detail.trim().to_string()),
+                None => DeviceState::Other(other.to_string()),
+            },
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            DeviceState::Device => "device",
+            DeviceState::Unauthorized => "unauthorized",
+            DeviceState::Authorizing => "authorizing",
+            DeviceState::Offline => "offline",
+            DeviceState::Connecting => "connecting",
+            DeviceState::NoPermissions(_) => "no permissions",
+            DeviceState::Recovery => "recovery",
+            DeviceState::Sideload => "sideload",
+            DeviceState::Bootloader => "bootloader",
+            DeviceState::Rescue => "rescue",
+            DeviceState::Other(state) => state,
+        }
+    }
+
+    /// What the user can do to get the device ready, or `None` when it is.
+    pub fn guidance(&self) -> Option<String> {
+        let text = match self {
+            DeviceState::Device => return None,
+            DeviceState::Unauthorized => {
+                "Unlock the device and accept the \"Allow USB debugging?\" prompt. If no prompt \
+                 appears, tap \"Revoke USB debugging authorizations\" in Developer options and \
+                 reconnect the cable."
+            }
+            DeviceState::Authorizing => {
+                "The device is checking this computer's debugging key. Unlock it and look for \
+                 a prompt."
+            }
+            DeviceState::Offline => {
+                "adb lost contact with the device. Unplug and reconnect it, or turn USB \
+                 debugging off and on again. If it stays offline, restart the adb server."
+            }
+            DeviceState::Connecting => {
+                "adb is still connecting to the device. Refresh in a moment."
+            }
+            DeviceState::NoPermissions(_) if cfg!(target_os = "linux") => {
+                "Your user isn't allowed to open the USB device. Install udev rules for Android \
+                 devices (e.g. the android-udev-rules package, or a rule with your vendor id in \
+                 /etc/udev/rules.d/51-android.rules), add yourself to the plugdev group, then \
+                 reconnect the device."
+            }
+            DeviceState::NoPermissions(_) => {
+                "The device can't be opened. Install the USB driver from the device's \
+                 manufacturer, then reconnect it."
+            }
+            DeviceState::Recovery => {
+                "The device is in recovery mode, where apps can't be changed. Reboot it into \
+                 Android."
+            }
+            DeviceState::Sideload => {
+                "The device is waiting for an `adb sideload` package. Cancel sideload on the \
+                 device and reboot it into Android."
+            }
+            DeviceState::Bootloader | DeviceState::Rescue => {
+                "The device is in its bootloader. Reboot it into Android."
+            }
+            DeviceState::Other(state) => {
+                return Some(format!(
+                    "adb reports the device as \"{}\". Reconnect it and check that USB \
+                     debugging is on.",
+                    state
+                ))
+            }
+        };
+        Some(text.to_string())
+    }
+}
+
+impl From<DeviceState> for String {
+    fn from(value: DeviceState) -> Self {
+        value.label().to_string()
+    }
+}
+
+impl fmt::Display for DeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// Shown when adb sees no device at all.
+pub const NO_DEVICE_GUIDANCE: &str = "Connect the device with a data-capable USB cable and turn \
+     on USB debugging in Developer options. Devices in the bootloader (fastboot) screen are \
+     invisible to adb; reboot them into Android first.";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Device {
+    pub serial: String,
+    pub state: DeviceState,
+    pub model: Option<String>,
+    pub transport_id: Option<String>,
+}
+
+impl Device {
+    pub fn is_authorized(&self) -> bool {
+        self.state == DeviceState::Device
+    }
+
+    /// Connected over Wi-Fi, either as `host:port` or as an mDNS service
+    /// name from wireless debugging.
+    pub fn is_wireless(&self) -> bool {
+        self.serial.contains(':') || self.serial.contains("._adb-tls-connect.")
+    }
+
+    pub fn display_name(&self) -> String {
+        match &self.model {
+            Some(model) => format!("{} ({})", model.replace('_', " "), self.serial),
+            None => self.serial.clone(),
+        }
+    }
+}
+
+/// Starts the adb server with the chosen adb binary. This is the only thing
+/// the binary is still used for; everything else talks to the server
+/// directly. Only runs when no server is listening, so there's never a
+/// server of another version to clash with.
+fn start_server() -> Result<(), String> {
+    const START_TIMEOUT: Duration = Duration::from_secs(30);
+    let adb = binary::adb()?;
+    // adb explains failures on stderr. It goes to a file rather than a pipe
+    // because the server it forks inherits the handle and never closes it.
+    let log_path = std::env::temp_dir().join(format!("purge-adb-start-{}.log", std::process::id()));
+    let log = File::create(&log_path)
+        .map_err(|e| format!("Failed to create {}: {}", log_path.display(), e))?;
+    let mut child = Command::new(&adb.path)
+        .arg("-P")
+        .arg(client::server_addr().port().to_string())
+        .arg("start-server")
+        .stdout(Stdio::null())
+        .stderr(log)
+        .spawn()
+        .map_err(|e| format!("Failed to start ADB server: {}", e))?;
+    let waited = wait_with_timeout(&mut child, START_TIMEOUT);
+    let output = fs::read_to_string(&log_path).unwrap_or_default();
+    let _ = fs::remove_file(&log_path);
+    match waited {
+        Ok(Some(status)) if status.success() => Ok(()),
+        Ok(Some(_)) => match output.lines().map(str::trim).rfind(|l| !l.is_empty()) {
+            Some(reason) => Err(format!("Failed to start ADB server: {}", reason)),
+            None => Err("Failed to start ADB server.".to_string()),
+        },
+        Ok(None) => Err(format!(
+            "ADB server did not start within {} seconds.",
+            START_TIMEOUT.as_secs()
+        )),
+        Err(e) => Err(format!("Failed to start ADB server: {}", e)),
+    }
+}
+
+/// The running adb server and the adb P.U.R.G.E. starts servers with.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    /// Protocol version of the running server, `None` when none is running.
+    pub server_version: Option<u32>,
+    /// Protocol version of the adb binary that would start a server.
+    pub adb_version: Option<u32>,
+    pub adb_path: Option<PathBuf>,
+}
+
+impl ServerStatus {
+    pub fn is_mismatched(&self) -> bool {
+        matches!(
+            (self.server_version, self.adb_version),
+            (Some(server), Some(adb)) if server != adb
+        )
+    }
+}
+
+/// The running server, which is always reused whatever adb started it: the
+/// wire client speaks to any version. Never starts a server that isn't
+/// running.
+pub fn server_status() -> Result<ServerStatus, String> {
+    let server_version = match client::server_version() {
+        Ok(version) => Some(version),
+        Err(e) if e.is_server_down() => None,
+        Err(e) => return Err(e.to_string()),
+    };
+    let adb = binary::adb().ok();
+    Ok(ServerStatus {
+        server_version,
+        adb_version: adb.and_then(|adb| adb.server_version()),
+        adb_path: adb.map(|adb| adb.path.clone()),
+    })
+}
+
+/// Stops whichever server is running, including one started by another
+/// tool, and starts a fresh one with the chosen adb.
+pub fn restart_server() -> Result<ServerStatus, String> {
+    kill_server()?;
+    start_server()?;
+    server_status()
+}
+
+fn kill_server() -> Result<(), String> {
+    const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+    match client::kill_server() {
+        Err(e) if e.is_server_down() => return Ok(()),
+        // The server may drop the connection as it exits.
+        _ => {}
+    }
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while Instant::now() < deadline {
+        if client::server_version().is_err_and(|e| e.is_server_down()) {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    Err(format!(
+        "ADB server did not stop within {} seconds.",
+        STOP_TIMEOUT.as_secs()
+    ))
+}
+
+/// Waits for `child` to exit, killing it once `timeout` has passed.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Runs a client call, starting the server and retrying once if nothing is
+/// listening yet.
+fn with_server<T>(mut call: impl FnMut() -> Result<T, client::ClientError>) -> Result<T, String> {
+    match call() {
+        Err(e) if e.is_server_down() => {
+            start_server()?;
+            call().map_err(|e| e.to_string())
+        }
+        result => result.map_err(|e| e.to_string()),
+    }
+}
+
+fn shell(serial: &str, command: &str) -> Result<client::ShellOutput, String> {
+    with_server(|| client::shell(serial, command))
+}
+
+fn is_valid_package_name(package: &str) -> bool {
+    !package.is_empty()
+        && package
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+}
+
+/// Keys of the `key:value` fields that follow the state in `adb devices -l`.
+const DEVICE_FIELDS: [&str; 5] = ["usb", "product", "model", "device", "transport_id"];
+
+/// Parses `adb devices -l` lines. The state may be several words, e.g.
+/// `no permissions (missing udev rules? ...); see [http://...]`, so it runs
+/// up to the first known `key:value` field.
+fn parse_devices(output: &str) -> Vec<Device> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("List of devices"))
+        .filter(|line| !line.starts_with('*'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().peekable();
+            let serial = fields.next()?.to_string();
+            let mut state = Vec::new();
+            while let Some(field) = fields.next_if(|field| {
+                !field
+                    .split_once(':')
+                    .is_some_and(|(key, _)| DEVICE_FIELDS.contains(&key))
+            }) {
+                state.push(field);
+            }
+            if state.is_empty() {
+                return None;
+            }
+            let mut device = Device {
+                serial,
+                state: DeviceState::parse(&state.join(" ")),
+                model: None,
+                transport_id: None,
+            };
+            for field in fields {
+                if let Some((key, value)) = field.split_once(':') {
+                    match key {
+                        "model" => device.model = Some(value.to_string()),
+                        "transport_id" => device.transport_id = Some(value.to_string()),
+                        _ => {}
+                    }
+                }
+            }
+            Some(device)
+        })
+        .collect()
+}
+
+pub fn list_devices() -> Result<Vec<Device>, String> {
+    Ok(parse_devices(&with_server(client::devices)?))
+}
+
+/// Calls `on_change` with every device listing the server pushes, until it
+/// returns `false` or the server goes away. Returns `Ok(false)` without
+/// calling `on_change` when the server refuses to track devices.
+pub fn track_devices(on_change: &mut dyn FnMut(Vec<Device>) -> bool) -> Result<bool, String> {
+    let tracker = with_server(|| match client::track_devices() {
+        Err(client::ClientError::Fail(_)) => Ok(None),
+        result => result.map(Some),
+    })?;
+    let Some(mut tracker) = tracker else {
+        return Ok(false);
+    };
+    loop {
+        let listing = tracker.next_listing().map_err(|e| e.to_string())?;
+        if !on_change(parse_devices(&listing)) {
+            return Ok(true);
+        }
+    }
+}
+
+pub fn get_device_model(serial: &str) -> Result<String, String> {
+    match shell(serial, "getprop ro.product.model") {
+        Ok(res) if res.succeeded() => {
+            let model = res.stdout.trim().to_string();
+            if model.is_empty() {
+                Err("Device model name is empty.".to_string())
+            } else {
+                Ok(model)
+            }
+        }
+        _ => Err("Could not retrieve device model.".to_string()),
+    }
+}
+
+/// The owner of the device, and the only user on most phones.
+pub const PRIMARY_USER: u32 = 0;
+
+/// A user or profile from `pm list users`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceUser {
+    pub id: u32,
+    pub name: String,
+    /// `UserInfo` flags, in the hex form the device prints them in.
+    pub flags: u32,
+    pub running: bool,
+}
+
+impl DeviceUser {
+    const FLAG_MANAGED_PROFILE: u32 = 0x20;
+
+    pub fn is_work_profile(&self) -> bool {
+        self.flags & Self::FLAG_MANAGED_PROFILE != 0
+    }
+
+    pub fn display_name(&self) -> String {
+        format!("{} ({})", self.name, self.id)
+    }
+}
+
+/// Parses lines like `UserInfo{10:Work profile:1030} running`.
+fn parse_users(output: &str) -> Vec<DeviceUser> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().strip_prefix("UserInfo{")?;
+            let (info, rest) = line.split_once('}')?;
+            let (id, info) = info.split_once(':')?;
+            // The name may contain ':', the flags never do.
+            let (name, flags) = info.rsplit_once(':')?;
+            Some(DeviceUser {
+                id: id.parse().ok()?,
+                name: name.to_string(),
+                flags: u32::from_str_radix(flags, 16).unwrap_or(0),
+                running: rest.trim() == "running",
+            })
+        })
+        .collect()
+}
+
+/// Every user and profile on the device, including work profiles and
+/// secondary users.
+pub fn list_users(serial: &str) -> Result<Vec<DeviceUser>, String> {
+    let res = shell(serial, "pm list users").map_err(|e| format!("Failed to list users: {}", e))?;
+    let users = parse_users(&res.stdout);
+    if users.is_empty() {
+        return Err("Could not list the users on the device.".to_string());
+    }
+    Ok(users)
+}
+
+fn pm_list_packages(serial: &str, user: u32, flags: &str) -> Result<Vec<String>, String> {
+    let command = format!("pm list packages --user {} {}", user, flags);
+    match shell(serial, command.trim_end()) {
+        Ok(res) => Ok(res
+            .stdout
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("package:"))
+            .map(str::to_string)
+            .collect()),
+        Err(e) => Err(format!("Failed to list packages: {}", e)),
+    }
+}
+
+pub fn list_packages(serial: &str, user: u32) -> Result<Vec<String>, String> {
+    pm_list_packages(serial, user, "")
+}
+
+/// Packages that still exist on the system image but are no longer installed
+/// for `user`, i.e. the ones `pm uninstall --user <user>` left behind.
+pub fn list_removed_packages(
+    serial: &str,
+    user: u32,
+    installed: &[String],
+) -> Result<Vec<String>, String> {
+    let installed: HashSet<&String> = installed.iter().collect();
+    Ok(pm_list_packages(serial, user, "-u")?
+        .into_iter()
+        .filter(|pkg| !installed.contains(pkg))
+        .collect())
+}
+
+/// Packages that are installed but disabled for `user`.
+pub fn list_disabled_packages(serial: &str, user: u32) -> Result<Vec<String>, String> {
+    pm_list_packages(serial, user, "-d")
+}
+
+/// Installed packages that `pm hide` or `pm suspend` took away from a user.
+/// Hidden packages only show up in `pm list packages -u`, like removed ones.
+#[derive(Debug, Default, PartialEq)]
+pub struct RestrictedPackages {
+    pub hidden: Vec<String>,
+    pub suspended: Vec<String>,
+}
+
+/// Reads the `hidden=` and `suspended=` flags of `user` from the `Packages:`
+/// section of `dumpsys package packages`. Packages not installed for the
+/// user count as removed, whatever their flags say.
+fn parse_restricted_packages(output: &str, user: u32) -> RestrictedPackages {
+    let user_prefix = format!("User {}:", user);
+    let mut restricted = RestrictedPackages::default();
+    let mut package = None;
+    let section = output
+        .lines()
+        .skip_while(|line| line.trim_end() != "Packages:")
+        .skip(1)
+        .take_while(|line| line.trim().is_empty() || line.starts_with(char::is_whitespace));
+    for line in section {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix("Package [") {
+            package = header.split_once(']').map(|(id, _)| id.to_string());
+            continue;
+        }
+        let (Some(fields), Some(id)) = (line.strip_prefix(&user_prefix), &package) else {
+            continue;
+        };
+        let set = |flag: &str| {
+            fields
+                .split_whitespace()
+                .any(|field| field.strip_prefix(flag) == Some("=true"))
+        };
+        if !set("installed") {
+            continue;
+        }
+        if set("hidden") {
+            restricted.hidden.push(id.clone());
+        }
+        if set("suspended") {
+            restricted.suspended.push(id.clone());
+        }
+    }
+    restricted
+}
+
+pub fn list_restricted_packages(serial: &str, user: u32) -> Result<RestrictedPackages, String> {
+    let res = shell(serial, "dumpsys package packages")
+        .map_err(|e| format!("Failed to list packages: {}", e))?;
+    Ok(parse_restricted_packages(&res.stdout, user))
+}
+
+/// An installed package as reported by `pm list packages -f -i`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstalledPackage {
+    pub id: String,
+    pub apk_path: String,
+    pub installer: Option<String>,
+    /// Part of the system image rather than installed by the user.
+    pub system: bool,
+}
+
+impl InstalledPackage {
+    pub fn kind_label(&self) -> &'static str {
+        if self.system {
+            "System"
+        } else {
+            "User"
+        }
+    }
+}
+
+/// Parses lines like
+/// `package:/data/app/com.foo-1/base.apk=com.foo  installer=com.android.vending`.
+fn parse_package_details(output: &str, system: bool) -> Vec<InstalledPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().strip_prefix("package:")?;
+            let (entry, installer) = match line.split_once("installer=") {
+                Some((entry, installer)) => (entry.trim(), installer.trim()),
+                None => (line, "null"),
+            };
+            // The path itself may contain '=', the package id never does.
+            let (apk_path, id) = entry.rsplit_once('=')?;
+            Some(InstalledPackage {
+                id: id.to_string(),
+                apk_path: apk_path.to_string(),
+                installer: Some(installer)
+                    .filter(|i| !i.is_empty() && *i != "null")
+                    .map(str::to_string),
+                system,
+            })
+        })
+        .collect()
+}
+
+/// APK path, installer and system/user flag for every package installed for
+/// `user`.
+pub fn list_package_details(serial: &str, user: u32) -> Result<Vec<InstalledPackage>, String> {
+    let mut packages = Vec::new();
+    for (flag, system) in [("-s", true), ("-3", false)] {
+        let res = shell(
+            serial,
+            &format!("pm list packages -f -i --user {} {}", user, flag),
+        )
+        .map_err(|e| format!("Failed to list packages: {}", e))?;
+        packages.extend(parse_package_details(&res.stdout, system));
+    }
+    Ok(packages)
+}
+
+/// Live details about one package, parsed from `dumpsys package <id>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PackageMetadata {
+    pub version_name: Option<String>,
+    pub version_code: Option<String>,
+    pub first_install_time: Option<String>,
+    pub last_update_time: Option<String>,
+    pub code_path: Option<String>,
+    /// The enabled setting for the requested user, e.g. `Disabled by user`.
+    pub enabled_state: Option<String>,
+    pub system: bool,
+    /// A system app that has been updated from the Play Store or OTA.
+    pub updated_system_app: bool,
+    pub granted_permissions: Vec<String>,
+}
+
+fn enabled_state_label(value: &str) -> String {
+    match value {
+        "0" => "Default".to_string(),
+        "1" => "Enabled".to_string(),
+        "2" => "Disabled".to_string(),
+        "3" => "Disabled by user".to_string(),
+        "4" => "Disabled until used".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Reads the first `Package [<id>]` block of `dumpsys package` output. The
+/// block ends at the first line indented no deeper than its header.
+fn parse_dumpsys_package(output: &str, package: &str, user: u32) -> Option<PackageMetadata> {
+    let header = format!("Package [{}]", package);
+    let mut lines = output
+        .lines()
+        .skip_while(|line| !line.trim().starts_with(&header));
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let header_indent = indent(lines.next()?);
+    let user_prefix = format!("User {}:", user);
+
+    let mut metadata = PackageMetadata::default();
+    let mut permissions = BTreeSet::new();
+    for line in lines.take_while(|line| line.trim().is_empty() || indent(line) > header_indent) {
+        let line = line.trim();
+        if let Some((permission, rest)) = line.split_once(": granted=") {
+            if rest.starts_with("true") {
+                permissions.insert(permission.to_string());
+            }
+            continue;
+        }
+        if let Some(flags) = line.strip_prefix("pkgFlags=") {
+            metadata.system = flags.split_whitespace().any(|f| f == "SYSTEM");
+            metadata.updated_system_app =
+                flags.split_whitespace().any(|f| f == "UPDATED_SYSTEM_APP");
+            continue;
+        }
+        if let Some(user) = line.strip_prefix(&user_prefix) {
+            metadata.enabled_state = user
+                .split_whitespace()
+                .find_map(|field| field.strip_prefix("enabled="))
+                .map(enabled_state_label);
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = || Some(value.to_string());
+            match key {
+                "versionName" => metadata.version_name = value(),
+                "codePath" => metadata.code_path = value(),
+                "firstInstallTime" => metadata.first_install_time = value(),
+                "lastUpdateTime" => metadata.last_update_time = value(),
+                "versionCode" => {
+                    metadata.version_code = line
+                        .split_whitespace()
+                        .next()
+                        .and_then(|field| field.strip_prefix("versionCode="))
+                        .map(str::to_string)
+                }
+                _ => {}
+            }
+        }
+    }
+    metadata.granted_permissions = permissions.into_iter().collect();
+    Some(metadata)
+}
+
+pub fn package_metadata(serial: &str, user: u32, package: &str) -> Result<PackageMetadata, String> {
+    if !is_valid_package_name(package) {
+        return Err(format!("Invalid package name '{}'", package));
+    }
+    let res = shell(serial, &format!("dumpsys package {}", package))?;
+    parse_dumpsys_package(&res.stdout, package, user)
+        .ok_or_else(|| format!("{} is not known to the package manager.", package))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageState {
+    Enabled,
+    Disabled,
+    Suspended,
+    Hidden,
+    Removed,
+}
+
+impl PackageState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PackageState::Enabled => "Enabled",
+            PackageState::Disabled => "Disabled",
+            PackageState::Suspended => "Suspended",
+            PackageState::Hidden => "Hidden",
+            PackageState::Removed => "Removed",
+        }
+    }
+
+    /// The purge action that leaves a package in this state, and so the one
+    /// to revert to get it back.
+    pub fn purge_action(&self) -> Option<PurgeAction> {
+        match self {
+            PackageState::Enabled => None,
+            PackageState::Disabled => Some(PurgeAction::Disable),
+            PackageState::Suspended => Some(PurgeAction::Suspend),
+            PackageState::Hidden => Some(PurgeAction::Hide),
+            PackageState::Removed => Some(PurgeAction::Uninstall),
+        }
+    }
+
+    /// Whether the package is still installed for the user, so the
+    /// Installed view lists it.
+    pub fn is_installed(&self) -> bool {
+        matches!(
+            self,
+            PackageState::Enabled | PackageState::Disabled | PackageState::Suspended
+        )
+    }
+}
+
+/// What a purge run does to each selected package.
+///
+/// `Suspend` needs Android 9+ and `Hide` usually needs root or a device owner,
+/// so those only work where the device allows them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PurgeAction {
+    Uninstall,
+    Disable,
+    Suspend,
+    Hide,
+}
+
+impl PurgeAction {
+    pub const ALL: [PurgeAction; 4] = [
+        PurgeAction::Uninstall,
+        PurgeAction::Disable,
+        PurgeAction::Suspend,
+        PurgeAction::Hide,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PurgeAction::Uninstall => "Uninstall",
+            PurgeAction::Disable => "Disable",
+            PurgeAction::Suspend => "Suspend",
+            PurgeAction::Hide => "Hide",
+        }
+    }
+
+    pub fn progress_verb(&self) -> &'static str {
+        match self {
+            PurgeAction::Uninstall => "Purging",
+            PurgeAction::Disable => "Disabling",
+            PurgeAction::Suspend => "Suspending",
+            PurgeAction::Hide => "Hiding",
+        }
+    }
+
+    fn pm_command(&self) -> &'static str {
+        match self {
+            PurgeAction::Uninstall => "pm uninstall",
+            PurgeAction::Disable => "pm disable-user",
+            PurgeAction::Suspend => "pm suspend",
+            PurgeAction::Hide => "pm hide",
+        }
+    }
+
+    fn revert_command(&self) -> &'static str {
+        match self {
+            PurgeAction::Uninstall => "cmd package install-existing",
+            PurgeAction::Disable => "pm enable",
+            PurgeAction::Suspend => "pm unsuspend",
+            PurgeAction::Hide => "pm unhide",
+        }
+    }
+
+    pub fn revert_label(&self) -> &'static str {
+        match self {
+            PurgeAction::Uninstall => "Restore",
+            PurgeAction::Disable => "Enable",
+            PurgeAction::Suspend => "Unsuspend",
+            PurgeAction::Hide => "Unhide",
+        }
+    }
+}
+
+/// Outcome of a single package manager call, parsed from its output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum PmOutcome {
+    Success,
+    /// The package is not installed for the target user, e.g.
+    /// `Failure [not installed for 0]`.
+    NotInstalled,
+    /// Any other failure, carrying the reason reported by the device,
+    /// e.g. `DELETE_FAILED_INTERNAL_ERROR`.
+    Failure(String),
+}
+
+impl PmOutcome {
+    pub fn is_success(&self) -> bool {
+        *self == PmOutcome::Success
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            PmOutcome::Success => "Success".to_string(),
+            PmOutcome::NotInstalled => "Not installed for this user".to_string(),
+            PmOutcome::Failure(reason) => reason.clone(),
+        }
+    }
+}
+
+/// What pm prints when a command worked: `Success` for installs and
+/// uninstalls, `Package x new state: disabled-user` and friends for state
+/// changes, `Package x installed for user: 0` for `install-existing`.
+const PM_SUCCESS_MARKERS: &[&str] = &[
+    "Success",
+    "new state:",
+    "new hidden state:",
+    "new suspended state:",
+    "installed for user",
+];
+
+/// Classifies pm's output. Without an exit code (no shell v2, or `exec`)
+/// only a success marker counts as success; silence isn't proof.
+fn parse_pm_output(exit_code: Option<u8>, stdout: &str, stderr: &str) -> PmOutcome {
+    let text = format!("{}\n{}", stdout.trim(), stderr.trim());
+    let text = text.trim();
+
+    if text.contains("not installed for") {
+        return PmOutcome::NotInstalled;
+    }
+    if let Some(line) = text.lines().find(|line| line.starts_with("Failure")) {
+        let reason = line
+            .trim_start_matches("Failure")
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim();
+        return PmOutcome::Failure(if reason.is_empty() {
+            "Unknown failure".to_string()
+        } else {
+            reason.to_string()
+        });
+    }
+    if let Some(line) = text
+        .lines()
+        .find(|line| line.starts_with("Error") || line.contains("Exception"))
+    {
+        return PmOutcome::Failure(line.trim().to_string());
+    }
+    let succeeded = match exit_code {
+        Some(code) => code == 0,
+        None => PM_SUCCESS_MARKERS
+            .iter()
+            .any(|marker| text.contains(marker)),
+    };
+    if succeeded {
+        PmOutcome::Success
+    } else if text.is_empty() && exit_code.is_none() {
+        PmOutcome::Failure("The command printed no result".to_string())
+    } else if text.is_empty() {
+        PmOutcome::Failure("Command exited with an error".to_string())
+    } else {
+        PmOutcome::Failure(text.lines().next().unwrap_or_default().to_string())
+    }
+}
+
+fn run_pm(serial: &str, user: u32, command: &str, package: &str) -> PmOutcome {
+    if !is_valid_package_name(package) {
+        return PmOutcome::Failure(format!("Invalid package name '{}'", package));
+    }
+    match shell(serial, &format!("{} --user {} {}", command, user, package)) {
+        Ok(res) => parse_pm_output(res.exit_code, &res.stdout, &res.stderr),
+        Err(e) => PmOutcome::Failure(e),
+    }
+}
+
+pub fn purge(serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+    run_pm(serial, user, action.pm_command(), package)
+}
+
+/// Undoes `action` for `package`: reinstalls, enables, unsuspends or unhides it.
+pub fn revert(serial: &str, user: u32, package: &str, action: PurgeAction) -> PmOutcome {
+    run_pm(serial, user, action.revert_command(), package)
+}
+
+/// Device paths of every APK in the package's split set, base APK first.
+pub fn apk_paths(serial: &str, user: u32, package: &str) -> Result<Vec<String>, String> {
+    if !is_valid_package_name(package) {
+        return Err(format!("Invalid package name '{}'", package));
+    }
+    let res = shell(serial, &format!("pm path --user {} {}", user, package))?;
+    let mut paths: Vec<String> = res
+        .stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("package:"))
+        .map(str::to_string)
+        .collect();
+    paths.sort_by_key(|path| !path.ends_with("/base.apk"));
+    Ok(paths)
+}
+
+pub fn pull_file(serial: &str, remote: &str, local: &Path) -> Result<(), String> {
+    let mut file =
+        File::create(local).map_err(|e| format!("Failed to create {}: {}", local.display(), e))?;
+    with_server(|| client::pull(serial, remote, &mut file)).map(|_| ())
+}
+
+fn exec(
+    serial: &str,
+    command: &str,
+    input: Option<&mut dyn std::io::Read>,
+) -> Result<String, String> {
+    match input {
+        Some(input) => client::exec(serial, command, Some(input)).map_err(|e| e.to_string()),
+        None => with_server(|| client::exec(serial, command, None)),
+    }
+}
+
+/// Installs an APK split set as one package for `user`, like
+/// `adb install-multiple -r --user <user>`.
+pub fn install_apks(serial: &str, user: u32, apks: &[PathBuf]) -> PmOutcome {
+    let created = match exec(
+        serial,
+        &format!("cmd package install-create -r --user {}", user),
+        None,
+    ) {
+        Ok(output) => output,
+        Err(e) => return PmOutcome::Failure(e),
+    };
+    let Some(session) = created
+        .split_once('[')
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(id, _)| id.to_string())
+    else {
+        return parse_pm_output(None, &created, "");
+    };
+
+    for (i, apk) in apks.iter().enumerate() {
+        let written = File::open(apk)
+            .and_then(|file| Ok((file.metadata()?.len(), file)))
+            .map_err(|e| format!("Failed to read {}: {}", apk.display(), e))
+            .and_then(|(size, mut file)| {
+                exec(
+                    serial,
+                    &format!(
+                        "cmd package install-write -S {} {} {}.apk -",
+                        size, session, i
+                    ),
+                    Some(&mut file),
+                )
+            });
+        let failure = match written {
+            Ok(output) if output.contains("Success") => None,
+            Ok(output) => Some(parse_pm_output(None, &output, "")),
+            Err(e) => Some(PmOutcome::Failure(e)),
+        };
+        if let Some(failure) = failure {
+            let _ = exec(
+                serial,
+                &format!("cmd package install-abandon {}", session),
+                None,
+            );
+            return failure;
+        }
+    }
+
+    match exec(
+        serial,
+        &format!("cmd package install-commit {}", session),
+        None,
+    ) {
+        Ok(output) => parse_pm_output(None, &output, ""),
+        Err(e) => PmOutcome::Failure(e),
+    }
+}
+
+/// Everything the package views need to know about one device. The package
+/// lists are for the user the snapshot was loaded for.
+#[derive(Debug)]
+pub struct DeviceSnapshot {
+    pub model: String,
+    pub user: u32,
+    pub users: Vec<DeviceUser>,
+    pub installed: Vec<String>,
+    /// Packages left on the system image but uninstalled for the user.
+    pub removed: Vec<String>,
+    pub hidden: Vec<String>,
+    pub disabled: Vec<String>,
+    pub suspended: Vec<String>,
+    pub details: Vec<InstalledPackage>,
+    /// The users each installed package is installed for, when the device
+    /// has more than one.
+    pub installed_for: HashMap<String, Vec<u32>>,
+}
+
+impl DeviceSnapshot {
+    /// `None` for packages the device doesn't have at all.
+    pub fn state_of(&self, id: &str) -> Option<PackageState> {
+        let has = |packages: &[String]| packages.iter().any(|p| p == id);
+        if has(&self.hidden) {
+            Some(PackageState::Hidden)
+        } else if has(&self.removed) {
+            Some(PackageState::Removed)
+        } else if has(&self.disabled) {
+            Some(PackageState::Disabled)
+        } else if has(&self.suspended) {
+            Some(PackageState::Suspended)
+        } else if has(&self.installed) {
+            Some(PackageState::Enabled)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn reboot_device(serial: &str) {
+    let _ = with_server(|| client::reboot(serial));
+}
+
+/// The port `adb tcpip` and `adb connect` use unless told otherwise.
+pub const DEFAULT_TCPIP_PORT: u16 = 5555;
+
+/// Checks a `host:port` address, adding `default_port` when the port is
+/// missing. Pairing has no default; its port changes every time. IPv6
+/// hosts need brackets, as in `[fe80::1]:5555`.
+pub fn normalize_endpoint(address: &str, default_port: Option<u16>) -> Result<String, String> {
+    let address = address.trim();
+    let invalid = || format!("'{}' is not a valid host:port address.", address);
+    if address.is_empty() || address.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    let (host, port) = if address.starts_with('[') {
+        let end = address.find(']').ok_or_else(invalid)? + 1;
+        let (host, rest) = address.split_at(end);
+        match rest {
+            "" => (host, None),
+            _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+        }
+    } else {
+        match address.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (address, None),
+        }
+    };
+    if host.is_empty() || host == "[]" || (!host.starts_with('[') && host.contains(':')) {
+        return Err(invalid());
+    }
+    match port {
+        Some(port) if port.parse::<u16>().is_ok_and(|p| p != 0) => Ok(address.to_string()),
+        Some(_) => Err(invalid()),
+        None => default_port
+            .map(|port| format!("{}:{}", host, port))
+            .ok_or_else(|| format!("'{}' is missing the port.", address)),
+    }
+}
+
+/// Pairs with the wireless debugging pairing service of an Android 11+
+/// device, like `adb pair`.
+pub fn pair(address: &str, code: &str) -> Result<String, String> {
+    let address = normalize_endpoint(address, None)?;
+    let code = code.trim();
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("The pairing code is the six digits shown on the device.".to_string());
+    }
+    let reply = with_server(|| client::pair(&address, code))?;
+    let reply = reply.trim();
+    if reply.starts_with("Successfully paired") {
+        Ok(reply.to_string())
+    } else {
+        Err(reply.to_string())
+    }
+}
+
+/// Connects to a device over TCP/IP, like `adb connect`, and returns the
+/// address it was reached at.
+pub fn connect(address: &str) -> Result<String, String> {
+    let address = normalize_endpoint(address, Some(DEFAULT_TCPIP_PORT))?;
+    let reply = with_server(|| client::connect(&address))?;
+    let reply = reply.trim();
+    if reply.starts_with("connected to") || reply.starts_with("already connected to") {
+        Ok(address)
+    } else {
+        Err(reply.to_string())
+    }
+}
+
+/// Disconnects one TCP/IP device, or all of them.
+pub fn disconnect(address: Option<&str>) -> Result<(), String> {
+    let address = address
+        .map(|a| normalize_endpoint(a, Some(DEFAULT_TCPIP_PORT)))
+        .transpose()?;
+    with_server(|| client::disconnect(address.as_deref().unwrap_or(""))).map(|_| ())
+}
+
+/// Reads the IPv4 address from `ip -f inet addr show wlan0` output.
+fn parse_inet_address(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let address = line
+            .trim()
+            .strip_prefix("inet ")?
+            .split_whitespace()
+            .next()?;
+        Some(address.split('/').next()?.to_string())
+    })
+}
+
+/// Restarts adbd on a USB device listening on `port`, like `adb tcpip`, for
+/// devices without wireless debugging. Returns the address to connect to
+/// when the device's Wi-Fi address could be read.
+pub fn enable_tcpip(serial: &str, port: u16) -> Result<Option<String>, String> {
+    // Read the address first; adbd drops the USB connection as it restarts.
+    let ip = shell(serial, "ip -f inet addr show wlan0")
+        .ok()
+        .and_then(|res| parse_inet_address(&res.stdout));
+    let reply = with_server(|| client::tcpip(serial, port))?;
+    if !reply.contains("restarting") {
+        return Err(reply.trim().to_string());
+    }
+    Ok(ip.map(|ip| format!("{}:{}", ip, port)))
+}
+
+/// Environment variable naming a fake device fixture to use instead of adb.
+pub const FAKE_DEVICE_ENV: &str = "PURGE_FAKE_DEVICE";
+
+/// The fake device from `fixture` if given, otherwise the one named by
+/// `PURGE_FAKE_DEVICE`, otherwise real devices through adb.
+pub fn backend(fixture: Option<&Path>) -> Result<Box<dyn DeviceBackend>, String> {
+    let from_env = std::env::var_os(FAKE_DEVICE_ENV).map(PathBuf::from);
+    match fixture.or(from_env.as_deref()) {
+        Some(path) => Ok(Box::new(FakeBackend::load(path)?)),
+        None => Ok(Box::new(AdbBackend)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_devices_reads_state_and_fields() {
+        let output = "List of devices attached\n\
+            * daemon started successfully\n\
+            ABC123         device usb:1-1 product:panther model:Pixel_7 device:panther transport_id:3\n\
+            192.168.1.20:5555 offline transport_id:5\n\
+            \n";
+        let devices = parse_devices(output);
+        assert_eq!(
+            devices,
+            [
+                Device {
+                    serial: "ABC123".to_string(),
+                    state: DeviceState::Device,
+                    model: Some("Pixel_7".to_string()),
+                    transport_id: Some("3".to_string()),
+                },
+                Device {
+                    serial: "192.168.1.20:5555".to_string(),
+                    state: DeviceState::Offline,
+                    model: None,
+                    transport_id: Some("5".to_string()),
+                },
+            ]
+        );
+        assert!(devices[0].is_authorized());
+        assert!(devices[1].is_wireless());
+    }
+
+    #[test]
+    fn parse_devices_keeps_multi_word_states() {
+        let output = "XYZ no permissions (missing udev rules? user is in the plugdev group); \
+            see [http://developer.android.com/tools/device.html] usb:1-2 transport_id:4\n";
+        let devices = parse_devices(output);
+        assert_eq!(devices.len(), 1);
+        assert!(matches!(devices[0].state, DeviceState::NoPermissions(_)));
+        assert_eq!(devices[0].transport_id.as_deref(), Some("4"));
+        assert!(devices[0].state.guidance().is_some());
+    }
+
+    #[test]
+    fn parse_users_reads_names_with_colons_and_flags() {
+        let output = "Users:\n\
+            \tUserInfo{0:Owner:c13} running\n\
+            \tUserInfo{10:Work: Acme:1030}\n";
+        let users = parse_users(output);
+        assert_eq!(
+            users,
+            [
+                DeviceUser {
+                    id: 0,
+                    name: "Owner".to_string(),
+                    flags: 0xc13,
+                    running: true,
+                },
+                DeviceUser {
+                    id: 10,
+                    name: "Work: Acme".to_string(),
+                    flags: 0x1030,
+                    running: false,
+                },
+            ]
+        );
+        assert!(!users[0].is_work_profile());
+        assert!(users[1].is_work_profile());
+    }
+
+    #[test]
+    fn parse_pm_output_classifies_results() {
+        assert_eq!(
+            parse_pm_output(Some(0), "Success\n", ""),
+            PmOutcome::Success
+        );
+        assert_eq!(
+            parse_pm_output(Some(1), "Failure [not installed for 0]\n", ""),
+            PmOutcome::NotInstalled
+        );
+        assert_eq!(
+            parse_pm_output(Some(1), "Failure [DELETE_FAILED_INTERNAL_ERROR]\n", ""),
+            PmOutcome::Failure("DELETE_FAILED_INTERNAL_ERROR".to_string())
+        );
+        assert_eq!(
+            parse_pm_output(Some(0), "", "Error: package com.x is not installed\n"),
+            PmOutcome::Failure("Error: package com.x is not installed".to_string())
+        );
+        assert_eq!(
+            parse_pm_output(
+                Some(255),
+                "",
+                "java.lang.SecurityException: Shell cannot change component state\n"
+            ),
+            PmOutcome::Failure(
+                "java.lang.SecurityException: Shell cannot change component state".to_string()
+            )
+        );
+        assert_eq!(
+            parse_pm_output(Some(1), "", ""),
+            PmOutcome::Failure("Command exited with an error".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_pm_output_without_an_exit_code_needs_a_success_marker() {
+        for output in [
+            "Success\n",
+            "Package com.x new state: disabled-user\n",
+            "Package com.x new state: enabled\n",
+            "Package com.x new hidden state: true\n",
+            "Package com.x new suspended state: false\n",
+            "Package com.x installed for user: 0\n",
+        ] {
+            assert_eq!(
+                parse_pm_output(None, output, ""),
+                PmOutcome::Success,
+                "{}",
+                output
+            );
+        }
+        assert_eq!(
+            parse_pm_output(None, "", ""),
+            PmOutcome::Failure("The command printed no result".to_string())
+        );
+        assert_eq!(
+            parse_pm_output(None, "Unknown command: frobnicate\n", ""),
+            PmOutcome::Failure("Unknown command: frobnicate".to_string())
+        );
+        assert_eq!(
+            parse_pm_output(None, "Failure [not installed for 0]\n", ""),
+            PmOutcome::NotInstalled
+        );
+    }
+
+    #[test]
+    fn parse_package_details_reads_path_id_and_installer() {
+        let output =
+            "package:/data/app/~~x==/com.foo-1/base.apk=com.foo  installer=com.android.vending\n\
+            package:/system/app/Bar/Bar.apk=com.bar  installer=null\n\
+            package:/system/app/Baz/Baz.apk=com.baz\n";
+        let packages = parse_package_details(output, false);
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].id, "com.foo");
+        assert_eq!(packages[0].apk_path, "/data/app/~~x==/com.foo-1/base.apk");
+        assert_eq!(
+            packages[0].installer.as_deref(),
+            Some("com.android.vending")
+        );
+        assert_eq!(packages[1].installer, None);
+        assert_eq!(packages[2].id, "com.baz");
+        assert_eq!(packages[2].installer, None);
+    }
+
+    #[test]
+    fn parse_dumpsys_package_reads_the_package_block() {
+        let output = "Packages:\n\
+            \x20 Package [com.other] (1):\n\
+            \x20   versionName=9.9\n\
+            \x20 Package [com.foo] (abc123):\n\
+            \x20   versionCode=42 minSdk=26 targetSdk=34\n\
+            \x20   versionName=1.2.3\n\
+            \x20   codePath=/data/app/com.foo-1\n\
+            \x20   pkgFlags=[ SYSTEM HAS_CODE UPDATED_SYSTEM_APP ]\n\
+            \x20   firstInstallTime=2008-12-31 16:00:00\n\
+            \x20   lastUpdateTime=2024-05-01 10:00:00\n\
+            \x20   User 0: ceDataInode=1 installed=true hidden=false enabled=3\n\
+            \x20   User 10: ceDataInode=2 installed=true hidden=false enabled=0\n\
+            \x20   runtime permissions:\n\
+            \x20     android.permission.CAMERA: granted=true\n\
+            \x20     android.permission.RECORD_AUDIO: granted=false\n\
+            \x20 Package [com.after] (2):\n\
+            \x20   versionName=0.1\n";
+        let metadata = parse_dumpsys_package(output, "com.foo", 10).unwrap();
+        assert_eq!(metadata.version_name.as_deref(), Some("1.2.3"));
+        assert_eq!(metadata.version_code.as_deref(), Some("42"));
+        assert_eq!(metadata.code_path.as_deref(), Some("/data/app/com.foo-1"));
+        assert_eq!(
+            metadata.first_install_time.as_deref(),
+            Some("2008-12-31 16:00:00")
+        );
+        assert_eq!(
+            metadata.last_update_time.as_deref(),
+            Some("2024-05-01 10:00:00")
+        );
+        assert_eq!(metadata.enabled_state.as_deref(), Some("Default"));
+        assert!(metadata.system);
+        assert!(metadata.updated_system_app);
+        assert_eq!(metadata.granted_permissions, ["android.permission.CAMERA"]);
+
+        let owner = parse_dumpsys_package(output, "com.foo", 0).unwrap();
+        assert_eq!(owner.enabled_state.as_deref(), Some("Disabled by user"));
+        assert!(parse_dumpsys_package(output, "com.missing", 0).is_none());
+    }
+
+    #[test]
+    fn parse_restricted_packages_reads_the_user_flags() {
+        let output = "Packages:\n\
+            \x20 Package [com.hidden] (1):\n\
+            \x20   User 0: ceDataInode=1 installed=true hidden=true suspended=false enabled=0\n\
+            \x20   User 10: ceDataInode=2 installed=true hidden=false suspended=true enabled=0\n\
+            \x20 Package [com.suspended] (2):\n\
+            \x20   User 0: ceDataInode=3 installed=true hidden=false suspended=true enabled=0\n\
+            \x20 Package [com.removed] (3):\n\
+            \x20   User 0: ceDataInode=0 installed=false hidden=true suspended=false enabled=0\n\
+            \n\
+            Hidden system packages:\n\
+            \x20 Package [com.updated] (4):\n\
+            \x20   User 0: installed=true hidden=true\n";
+        assert_eq!(
+            parse_restricted_packages(output, 0),
+            RestrictedPackages {
+                hidden: vec!["com.hidden".to_string()],
+                suspended: vec!["com.suspended".to_string()],
+            }
+        );
+        assert_eq!(
+            parse_restricted_packages(output, 10).suspended,
+            ["com.hidden"]
+        );
+    }
+
+    #[test]
+    fn normalize_endpoint_adds_the_default_port_only_when_missing() {
+        assert_eq!(
+            normalize_endpoint("192.168.1.20", Some(5555)).unwrap(),
+            "192.168.1.20:5555"
+        );
+        assert_eq!(
+            normalize_endpoint("192.168.1.20:37000", Some(5555)).unwrap(),
+            "192.168.1.20:37000"
+        );
+        let error = normalize_endpoint("192.168.1.20", None).unwrap_err();
+        assert!(error.contains("missing the port"), "{}", error);
+    }
+
+    #[test]
+    fn normalize_endpoint_trims_surrounding_whitespace() {
+        assert_eq!(
+            normalize_endpoint("  phone.local:5555\n", None).unwrap(),
+            "phone.local:5555"
+        );
+        assert!(normalize_endpoint("phone local:5555", None).is_err());
+        assert!(normalize_endpoint("   ", Some(5555)).is_err());
+    }
+
+    #[test]
+    fn normalize_endpoint_rejects_bad_ports() {
+        for address in ["host:0", "host:65536", "host:-1", "host:port", "host:"] {
+            assert!(
+                normalize_endpoint(address, Some(5555)).is_err(),
+                "{} was accepted",
+                address
+            );
+        }
+        assert_eq!(
+            normalize_endpoint("host:65535", None).unwrap(),
+            "host:65535"
+        );
+    }
+
+    #[test]
+    fn normalize_endpoint_rejects_an_empty_host() {
+        assert!(normalize_endpoint(":5555", None).is_err());
+        assert!(normalize_endpoint("[]:5555", None).is_err());
+    }
+
+    #[test]
+    fn normalize_endpoint_accepts_bracketed_ipv6() {
+        assert_eq!(
+            normalize_endpoint("[::1]:5555", None).unwrap(),
+            "[::1]:5555"
+        );
+        assert_eq!(
+            normalize_endpoint("[fe80::1]", Some(5555)).unwrap(),
+            "[fe80::1]:5555"
+        );
+        assert!(normalize_endpoint("[::1]", None).is_err());
+        assert!(normalize_endpoint("[::1]5555", None).is_err());
+        assert!(normalize_endpoint("[::1:5555", None).is_err());
+        // Without brackets the port can't be told apart from the address.
+        assert!(normalize_endpoint("::1", Some(5555)).is_err());
+    }
+
+    #[test]
+    fn parse_inet_address_strips_the_prefix_length() {
+        let output = "3: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500\n\
+            \x20   inet 192.168.1.20/24 brd 192.168.1.255 scope global wlan0\n";
+        assert_eq!(parse_inet_address(output).as_deref(), Some("192.168.1.20"));
+        assert_eq!(parse_inet_address(""), None);
+    }
+}
@@ -0,0 +1,333 @@
+// src/backup.rs
+use crate::adb::{DeviceBackend, PmOutcome, PurgeAction};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One backed-up package: its APK split set, stored next to the manifest in
+/// a folder named after the package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub package: String,
+    /// File names inside the package folder, base APK first.
+    pub files: Vec<String>,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub app_version: String,
+}
+
+/// `manifest.json` in a device's backup folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub packages: Vec<BackupEntry>,
+}
+
+impl Manifest {
+    pub fn get(&self, package: &str) -> Option<&BackupEntry> {
+        self.packages.iter().find(|entry| entry.package == package)
+    }
+}
+
+pub fn backups_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("purge").join("backups"))
+}
+
+/// The backup folder for one device. Serials of network devices contain
+/// `:`, which isn't allowed in Windows paths.
+pub fn device_dir(serial: &str) -> Result<PathBuf, String> {
+    let name: String = serial
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    backups_dir()
+        .map(|dir| dir.join(name))
+        .ok_or_else(|| "No data directory available for backups.".to_string())
+}
+
+fn manifest_path(dir: &Path) -> PathBuf {
+    dir.join("manifest.json")
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let path = manifest_path(dir);
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid backup manifest {}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Writes the manifest through a temporary file so a crash can't leave it
+/// half written.
+fn save_manifest(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let path = manifest_path(dir);
+    let tmp = path.with_extension("json.tmp");
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    fs::write(&tmp, json)
+        .and_then(|_| fs::rename(&tmp, &path))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Pulls every APK of `package` into the device's backup folder and records
+/// it in the manifest, replacing any earlier backup of the same package.
+pub fn backup_package(
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+) -> Result<BackupEntry, String> {
+    backup_into(&device_dir(serial)?, backend, serial, user, package)
+}
+
+fn backup_into(
+    dir: &Path,
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+) -> Result<BackupEntry, String> {
+    let remote_paths = backend.apk_paths(serial, user, package)?;
+    if remote_paths.is_empty() {
+        return Err(format!("No APK found for {}.", package));
+    }
+
+    let target = dir.join(package);
+    let partial = dir.join(format!("{}.partial", package));
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+
+    let mut files = Vec::with_capacity(remote_paths.len());
+    for remote in &remote_paths {
+        let name = remote.rsplit('/').next().unwrap_or(remote).to_string();
+        if let Err(e) = backend.pull_file(serial, remote, &partial.join(&name)) {
+            let _ = fs::remove_dir_all(&partial);
+            return Err(e);
+        }
+        files.push(name);
+    }
+
+    // Only replace the previous backup once the new one is complete.
+    let _ = fs::remove_dir_all(&target);
+    fs::rename(&partial, &target)
+        .map_err(|e| format!("Failed to move backup into {}: {}", target.display(), e))?;
+
+    let entry = BackupEntry {
+        package: package.to_string(),
+        files,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    let mut manifest = read_manifest(dir)?;
+    manifest.packages.retain(|e| e.package != package);
+    manifest.packages.push(entry.clone());
+    save_manifest(dir, &manifest)?;
+    Ok(entry)
+}
+
+/// Reinstalls `package` for `user` from its backup, like
+/// `adb install-multiple -r`.
+fn restore_from(
+    dir: &Path,
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+) -> PmOutcome {
+    let manifest = match read_manifest(dir) {
+        Ok(manifest) => manifest,
+        Err(e) => return PmOutcome::Failure(e),
+    };
+    let Some(entry) = manifest.get(package) else {
+        return PmOutcome::Failure(format!("No backup of {} for this device", package));
+    };
+    let apks: Vec<PathBuf> = entry
+        .files
+        .iter()
+        .map(|name| dir.join(package).join(name))
+        .collect();
+    if let Some(missing) = apks.iter().find(|apk| !apk.is_file()) {
+        return PmOutcome::Failure(format!("Backup file {} is missing", missing.display()));
+    }
+    backend.install_apks(serial, user, package, &apks)
+}
+
+/// Uninstalls `package` only after its APKs were backed up. Other actions
+/// keep the APK on the device, so they run without a backup.
+pub fn purge_with_backup(
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+    action: PurgeAction,
+) -> PmOutcome {
+    if action != PurgeAction::Uninstall {
+        return backend.purge(serial, user, package, action);
+    }
+    match backup_package(backend, serial, user, package) {
+        Ok(_) => backend.purge(serial, user, package, action),
+        Err(e) => PmOutcome::Failure(format!("Backup failed, not uninstalled: {}", e)),
+    }
+}
+
+/// Undoes an uninstall from the backup when there is one, otherwise reverts
+/// `action` as usual.
+pub fn revert_preferring_backup(
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+    action: PurgeAction,
+) -> PmOutcome {
+    match device_dir(serial) {
+        Ok(dir) => revert_from(&dir, backend, serial, user, package, action),
+        Err(_) => backend.revert(serial, user, package, action),
+    }
+}
+
+fn revert_from(
+    dir: &Path,
+    backend: &dyn DeviceBackend,
+    serial: &str,
+    user: u32,
+    package: &str,
+    action: PurgeAction,
+) -> PmOutcome {
+    let has_backup = read_manifest(dir).is_ok_and(|manifest| manifest.get(package).is_some());
+    if action == PurgeAction::Uninstall && has_backup {
+        restore_from(dir, backend, serial, user, package)
+    } else {
+        backend.revert(serial, user, package, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeBackend, PRIMARY_USER};
+
+    const SERIAL: &str = "FAKE0001";
+
+    fn fixture() -> FakeBackend {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/fake_device.json");
+        FakeBackend::load(&path).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("purge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn is_installed(backend: &FakeBackend, package: &str) -> bool {
+        backend
+            .list_packages(SERIAL, PRIMARY_USER)
+            .unwrap()
+            .contains(&package.to_string())
+    }
+
+    #[test]
+    fn backup_writes_the_manifest_and_moves_the_partial_folder() {
+        let dir = temp_dir("backup-manifest");
+        let package = "com.example.sideloaded";
+        let entry = backup_into(&dir, &fixture(), SERIAL, PRIMARY_USER, package).unwrap();
+        assert_eq!(entry.files, vec!["base.apk".to_string()]);
+
+        assert!(dir.join(package).join("base.apk").is_file());
+        assert!(!dir.join(format!("{}.partial", package)).exists());
+        assert!(!dir.join("manifest.json.tmp").exists());
+        let manifest = read_manifest(&dir).unwrap();
+        assert_eq!(manifest.get(package).unwrap().files, entry.files);
+
+        // A second backup replaces the first instead of adding to it.
+        backup_into(&dir, &fixture(), SERIAL, PRIMARY_USER, package).unwrap();
+        assert_eq!(read_manifest(&dir).unwrap().packages.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backup_of_a_missing_package_fails_without_a_manifest() {
+        let dir = temp_dir("backup-missing");
+        let error = backup_into(
+            &dir,
+            &fixture(),
+            SERIAL,
+            PRIMARY_USER,
+            "com.example.missing",
+        );
+        assert!(error.is_err());
+        assert!(!dir.join("manifest.json").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backup_then_restore_round_trips() {
+        let dir = temp_dir("backup-restore");
+        let backend = fixture();
+        let package = "com.example.sideloaded";
+        backup_into(&dir, &backend, SERIAL, PRIMARY_USER, package).unwrap();
+        backend.purge(SERIAL, PRIMARY_USER, package, PurgeAction::Uninstall);
+        assert!(!is_installed(&backend, package));
+
+        let outcome = restore_from(&dir, &backend, SERIAL, PRIMARY_USER, package);
+        assert_eq!(outcome, PmOutcome::Success);
+        assert!(is_installed(&backend, package));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn revert_prefers_the_backup_for_uninstalls() {
+        let dir = temp_dir("backup-prefer");
+        let backend = fixture();
+        let package = "com.example.sideloaded";
+        backup_into(&dir, &backend, SERIAL, PRIMARY_USER, package).unwrap();
+        backend.purge(SERIAL, PRIMARY_USER, package, PurgeAction::Uninstall);
+
+        // With the APK gone, only the backup route can fail this way.
+        fs::remove_file(dir.join(package).join("base.apk")).unwrap();
+        let outcome = revert_from(
+            &dir,
+            &backend,
+            SERIAL,
+            PRIMARY_USER,
+            package,
+            PurgeAction::Uninstall,
+        );
+        match outcome {
+            PmOutcome::Failure(e) => assert!(e.contains("is missing"), "{}", e),
+            other => panic!("expected the backup to be used, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn revert_without_a_backup_uses_the_device() {
+        let dir = temp_dir("backup-none");
+        let backend = fixture();
+        let package = "com.google.android.youtube";
+        backend.purge(SERIAL, PRIMARY_USER, package, PurgeAction::Uninstall);
+        let outcome = revert_from(
+            &dir,
+            &backend,
+            SERIAL,
+            PRIMARY_USER,
+            package,
+            PurgeAction::Uninstall,
+        );
+        assert_eq!(outcome, PmOutcome::Success);
+        assert!(is_installed(&backend, package));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
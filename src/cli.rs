@@ -0,0 +1,443 @@
+// src/cli.rs
+use crate::adb::{self, DeviceBackend};
+use crate::{backup, config, journal, wireless};
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "purge",
+    version,
+    about = "P.U.R.G.E. - Package Uninstaller and Resource & Garbage Eliminator",
+    long_about = "Run without arguments to open the GUI, or use a subcommand for headless, scriptable debloating. All results are printed as JSON."
+)]
+struct Cli {
+    /// Serial of the device to target (see `purge devices`). Optional when
+    /// exactly one authorized device is attached.
+    #[arg(short, long, global = true)]
+    serial: Option<String>,
+
+    /// Id of the user or profile to list and act on (see `purge users`).
+    /// Defaults to the primary user, 0.
+    #[arg(short, long, global = true, value_name = "ID")]
+    user: Option<u32>,
+
+    /// Use a fake device loaded from this JSON fixture instead of adb. Also
+    /// read from the PURGE_FAKE_DEVICE environment variable.
+    #[arg(long, global = true, value_name = "FIXTURE")]
+    fake_device: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// List attached devices and their state.
+    Devices,
+    /// List the users and profiles on the device, including work profiles.
+    Users,
+    /// List packages from the debloat lists.
+    List(ListArgs),
+    /// Uninstall, disable, suspend or hide packages.
+    Uninstall {
+        #[command(flatten)]
+        targets: Targets,
+        /// What to do with each package.
+        #[arg(long, value_enum, default_value = "uninstall")]
+        action: adb::PurgeAction,
+        /// Back up each package's APKs before uninstalling it, and skip
+        /// packages whose backup fails.
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Bring back packages removed for the user, or hidden, disabled or
+    /// suspended for it, by undoing whichever action applies.
+    Restore {
+        #[command(flatten)]
+        targets: Targets,
+        /// Restore every removed or hidden package known to the debloat
+        /// lists.
+        #[arg(long, conflicts_with_all = ["ids", "from_file"])]
+        all: bool,
+        /// Reinstall from APK backups where one exists.
+        #[arg(long)]
+        from_backup: bool,
+    },
+    /// Pair with a device over Wi-Fi using Android 11+ wireless debugging.
+    Pair {
+        /// The host:port shown under "Pair device with pairing code".
+        address: String,
+        /// The six-digit pairing code shown on the device.
+        code: String,
+    },
+    /// Connect to a device over TCP/IP and remember the address. Without an
+    /// address, reconnects every remembered one.
+    Connect {
+        /// host:port, or just the host to use port 5555.
+        address: Option<String>,
+    },
+    /// Disconnect a TCP/IP device, or all of them.
+    Disconnect {
+        address: Option<String>,
+        /// Also forget the remembered address.
+        #[arg(long, requires = "address")]
+        forget: bool,
+    },
+    /// Restart adbd on a USB device in TCP/IP mode, for devices without
+    /// wireless debugging. Connect to the printed address afterwards.
+    Tcpip {
+        #[arg(long, default_value_t = adb::DEFAULT_TCPIP_PORT)]
+        port: u16,
+    },
+    /// Show the running adb server's version and the adb used to start one.
+    Server {
+        /// Kill the running server, whoever started it, and start a new one.
+        #[arg(long)]
+        restart: bool,
+    },
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Only packages from this list (e.g. Oem, Google, Carrier).
+    #[arg(long, value_parser = str::parse::<config::PackageList>)]
+    list: Option<config::PackageList>,
+    /// Only packages with this removal level (e.g. Safe, Advanced).
+    #[arg(long, value_parser = str::parse::<config::Removal>)]
+    removal: Option<config::Removal>,
+    /// Only packages whose id contains this text.
+    #[arg(long, default_value = "")]
+    search: String,
+    /// Only packages installed on the device.
+    #[arg(long, conflicts_with = "removed")]
+    installed: bool,
+    /// Only packages removed from the device but still restorable.
+    #[arg(long)]
+    removed: bool,
+}
+
+#[derive(Args)]
+struct Targets {
+    /// Package ids to act on.
+    ids: Vec<String>,
+    /// Read package ids from a file, one per line. Blank lines and lines
+    /// starting with `#` are ignored.
+    #[arg(long)]
+    from_file: Option<PathBuf>,
+}
+
+impl Targets {
+    fn resolve(&self) -> Result<Vec<String>, String> {
+        let mut ids = self.ids.clone();
+        if let Some(path) = &self.from_file {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            ids.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        let mut seen = HashSet::new();
+        ids.retain(|id| seen.insert(id.clone()));
+        if ids.is_empty() {
+            Err("No package ids given.".to_string())
+        } else {
+            Ok(ids)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ListedPackage<'a> {
+    #[serde(flatten)]
+    info: &'a config::PackageInfo,
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<adb::PackageState>,
+    /// Ids of the users the package is installed for, on devices with more
+    /// than one user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    installed_for: Option<Vec<u32>>,
+}
+
+#[derive(Serialize)]
+struct DeviceReport {
+    #[serde(flatten)]
+    device: adb::Device,
+    /// What to do when the device isn't ready.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guidance: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WirelessResult {
+    /// Missing when the command covered every TCP/IP device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    success: bool,
+    message: String,
+}
+
+impl WirelessResult {
+    fn new(address: Option<&str>, result: Result<String, String>) -> Self {
+        let success = result.is_ok();
+        Self {
+            address: address.map(str::to_string),
+            success,
+            message: result.unwrap_or_else(|e| e),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PackageResult {
+    package: String,
+    #[serde(flatten)]
+    outcome: adb::PmOutcome,
+}
+
+/// Runs the command line interface and returns the process exit code:
+/// 0 on success, 1 on error, 2 when some packages failed.
+pub fn run() -> i32 {
+    let cli = Cli::parse();
+    match execute(cli) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn execute(cli: Cli) -> Result<i32, String> {
+    let backend = adb::backend(cli.fake_device.as_deref())?;
+    let backend = backend.as_ref();
+    let serial = cli.serial.as_deref();
+    match cli.command {
+        CliCommand::Devices => {
+            let devices: Vec<DeviceReport> = backend
+                .list_devices()?
+                .into_iter()
+                .map(|device| DeviceReport {
+                    guidance: device.state.guidance(),
+                    device,
+                })
+                .collect();
+            print_json(&devices)?;
+            Ok(0)
+        }
+        CliCommand::Users => {
+            let serial = backend.resolve_serial(serial)?;
+            print_json(&backend.list_users(&serial)?)?;
+            Ok(0)
+        }
+        CliCommand::List(args) => list(backend, serial, cli.user, args),
+        CliCommand::Uninstall {
+            targets,
+            action,
+            backup,
+        } => {
+            let serial = backend.resolve_serial(serial)?;
+            let user = backend.resolve_user(&serial, cli.user)?;
+            let ids = targets.resolve()?;
+            let session = journal::new_session();
+            let results: Vec<PackageResult> = ids
+                .into_iter()
+                .map(|package| {
+                    let outcome = if backup {
+                        backup::purge_with_backup(backend, &serial, user, &package, action)
+                    } else {
+                        backend.purge(&serial, user, &package, action)
+                    };
+                    journal::record(session, &serial, user, &package, action, false, &outcome);
+                    PackageResult { package, outcome }
+                })
+                .collect();
+            report(&results)
+        }
+        CliCommand::Restore {
+            targets,
+            all,
+            from_backup,
+        } => {
+            let serial = backend.resolve_serial(serial)?;
+            let user = backend.resolve_user(&serial, cli.user)?;
+            let snapshot = backend.load_device(&serial, user)?;
+            let ids = if all {
+                load_packages()?
+                    .into_iter()
+                    .map(|info| info.id)
+                    .filter(|id| snapshot.state_of(id).is_some_and(|s| !s.is_installed()))
+                    .collect()
+            } else {
+                targets.resolve()?
+            };
+            let session = journal::new_session();
+            let results: Vec<PackageResult> = ids
+                .into_iter()
+                .map(|package| {
+                    // Undo whatever took the package away; packages that
+                    // look untouched get the plain reinstall.
+                    let action = snapshot
+                        .state_of(&package)
+                        .and_then(|state| state.purge_action())
+                        .unwrap_or(adb::PurgeAction::Uninstall);
+                    let outcome = if from_backup {
+                        backup::revert_preferring_backup(backend, &serial, user, &package, action)
+                    } else {
+                        backend.revert(&serial, user, &package, action)
+                    };
+                    journal::record(session, &serial, user, &package, action, true, &outcome);
+                    PackageResult { package, outcome }
+                })
+                .collect();
+            report(&results)
+        }
+        CliCommand::Pair { address, code } => {
+            let result = WirelessResult::new(Some(&address), backend.pair(&address, &code));
+            print_json(&result)?;
+            Ok(if result.success { 0 } else { 2 })
+        }
+        CliCommand::Connect { address } => {
+            let addresses = match address {
+                Some(address) => vec![address],
+                None => {
+                    let remembered: Vec<String> =
+                        wireless::load()?.into_iter().map(|e| e.address).collect();
+                    if remembered.is_empty() {
+                        return Err("No remembered addresses; pass one to connect.".to_string());
+                    }
+                    remembered
+                }
+            };
+            let results: Vec<WirelessResult> = addresses
+                .iter()
+                .map(|address| {
+                    let result = backend.connect(address).map(|connected| {
+                        if let Err(e) = wireless::remember(&connected) {
+                            eprintln!("warning: {}", e);
+                        }
+                        format!("Connected to {}", connected)
+                    });
+                    WirelessResult::new(Some(address), result)
+                })
+                .collect();
+            print_json(&results)?;
+            Ok(if results.iter().all(|r| r.success) {
+                0
+            } else {
+                2
+            })
+        }
+        CliCommand::Disconnect { address, forget } => {
+            let address = address
+                .map(|a| adb::normalize_endpoint(&a, Some(adb::DEFAULT_TCPIP_PORT)))
+                .transpose()?;
+            let result = backend
+                .disconnect(address.as_deref())
+                .map(|()| match &address {
+                    Some(address) => format!("Disconnected {}", address),
+                    None => "Disconnected every TCP/IP device".to_string(),
+                });
+            if let (Some(address), true, Ok(_)) = (&address, forget, &result) {
+                wireless::forget(address)?;
+            }
+            let result = WirelessResult::new(address.as_deref(), result);
+            print_json(&result)?;
+            Ok(if result.success { 0 } else { 2 })
+        }
+        CliCommand::Tcpip { port } => {
+            let serial = backend.resolve_serial(serial)?;
+            let address = backend.enable_tcpip(&serial, port)?;
+            print_json(&serde_json::json!({ "serial": serial, "address": address }))?;
+            Ok(0)
+        }
+        CliCommand::Server { restart } => {
+            let status = if restart {
+                backend.restart_server()?
+            } else {
+                backend.server_status()?
+            };
+            print_json(&status)?;
+            Ok(0)
+        }
+    }
+}
+
+fn list(
+    backend: &dyn DeviceBackend,
+    serial: Option<&str>,
+    user: Option<u32>,
+    args: ListArgs,
+) -> Result<i32, String> {
+    let packages = load_packages()?;
+    let filter = config::PackageFilter {
+        search: &args.search,
+        list: args.list.as_ref(),
+        removal: args.removal.as_ref(),
+    };
+
+    let snapshot = if args.installed || args.removed || serial.is_some() || user.is_some() {
+        let serial = backend.resolve_serial(serial)?;
+        let user = backend.resolve_user(&serial, user)?;
+        Some(backend.load_device(&serial, user)?)
+    } else {
+        None
+    };
+    let state_of = |id: &str| snapshot.as_ref().and_then(|s| s.state_of(id));
+
+    let listed: Vec<ListedPackage> = packages
+        .iter()
+        .filter(|info| filter.matches(info))
+        .map(|info| ListedPackage {
+            info,
+            source: info.source.to_string(),
+            state: state_of(&info.id),
+            installed_for: snapshot.as_ref().and_then(|s| {
+                (s.users.len() > 1)
+                    .then(|| s.installed_for.get(&info.id).cloned().unwrap_or_default())
+            }),
+        })
+        .filter(|p| {
+            if args.installed {
+                p.state.is_some_and(|state| state.is_installed())
+            } else if args.removed {
+                p.state.is_some_and(|state| !state.is_installed())
+            } else {
+                true
+            }
+        })
+        .collect();
+    print_json(&listed)?;
+    Ok(0)
+}
+
+fn load_packages() -> Result<Vec<config::PackageInfo>, String> {
+    let loaded = config::load_all_lists().map_err(|e| e.to_string())?;
+    for e in &loaded.errors {
+        eprintln!("warning: {}", e);
+    }
+    Ok(loaded.packages)
+}
+
+fn report(results: &[PackageResult]) -> Result<i32, String> {
+    print_json(&results)?;
+    if results.iter().all(|r| r.outcome.is_success()) {
+        Ok(0)
+    } else {
+        Ok(2)
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
@@ -1,20 +1,548 @@
 // config.rs
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize, Clone)]
+/// Where a package entry was loaded from.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ListSource {
+    #[default]
+    Embedded,
+    File(PathBuf),
+    /// Installed on the device but absent from every loaded list.
+    Device,
+}
+
+impl fmt::Display for ListSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListSource::Embedded => write!(f, "Built-in list"),
+            ListSource::File(path) => write!(f, "{}", path.display()),
+            ListSource::Device => write!(f, "Not in any debloat list"),
+        }
+    }
+}
+
+/// How risky a package is to remove, as rated by the debloat lists. Ordered
+/// from least to most risky, with unrated packages last.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Removal {
+    Safe,
+    Advanced,
+    Expert,
+    Unsafe,
+    System,
+    /// A level this version doesn't know, e.g. from a custom list.
+    Other(String),
+    Unknown,
+}
+
+impl Removal {
+    pub const ALL: [Removal; 6] = [
+        Removal::Safe,
+        Removal::Advanced,
+        Removal::Expert,
+        Removal::Unsafe,
+        Removal::System,
+        Removal::Unknown,
+    ];
+
+    /// Parses a level case-insensitively, so `safe` and `Safe` are the same.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        Removal::ALL
+            .into_iter()
+            .find(|r| r.label().eq_ignore_ascii_case(value))
+            .unwrap_or_else(|| {
+                if value.is_empty() {
+                    Removal::Unknown
+                } else {
+                    Removal::Other(value.to_string())
+                }
+            })
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Removal::Safe => "Safe",
+            Removal::Advanced => "Advanced",
+            Removal::Expert => "Expert",
+            Removal::Unsafe => "Unsafe",
+            Removal::System => "System",
+            Removal::Other(name) => name,
+            Removal::Unknown => "Unknown",
+        }
+    }
+
+    /// Levels whose removal can leave the device unusable.
+    pub fn is_risky(&self) -> bool {
+        matches!(self, Removal::Expert | Removal::Unsafe)
+    }
+}
+
+/// The debloat list a package belongs to, usually its vendor. Ordered by
+/// name, with lists this version doesn't know last.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum PackageList {
+    Amazon,
+    AntPlus,
+    Aosp,
+    Asus,
+    Carrier,
+    Facebook,
+    Fonts,
+    Google,
+    Huawei,
+    Lg,
+    Microsoft,
+    Misc,
+    Motorola,
+    Nokia,
+    Oem,
+    OnePlus,
+    Pending,
+    Qualcomm,
+    Samsung,
+    Sony,
+    Xiaomi,
+    Other(String),
+}
+
+impl PackageList {
+    const KNOWN: [PackageList; 21] = [
+        PackageList::Amazon,
+        PackageList::AntPlus,
+        PackageList::Aosp,
+        PackageList::Asus,
+        PackageList::Carrier,
+        PackageList::Facebook,
+        PackageList::Fonts,
+        PackageList::Google,
+        PackageList::Huawei,
+        PackageList::Lg,
+        PackageList::Microsoft,
+        PackageList::Misc,
+        PackageList::Motorola,
+        PackageList::Nokia,
+        PackageList::Oem,
+        PackageList::OnePlus,
+        PackageList::Pending,
+        PackageList::Qualcomm,
+        PackageList::Samsung,
+        PackageList::Sony,
+        PackageList::Xiaomi,
+    ];
+
+    /// Parses a list name case-insensitively, so `AOSP` and `Aosp` are the
+    /// same list.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        PackageList::KNOWN
+            .into_iter()
+            .find(|l| l.label().eq_ignore_ascii_case(value))
+            .unwrap_or_else(|| PackageList::Other(value.to_string()))
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            PackageList::Amazon => "Amazon",
+            PackageList::AntPlus => "ANT+",
+            PackageList::Aosp => "Aosp",
+            PackageList::Asus => "Asus",
+            PackageList::Carrier => "Carrier",
+            PackageList::Facebook => "Facebook",
+            PackageList::Fonts => "Fonts",
+            PackageList::Google => "Google",
+            PackageList::Huawei => "Huawei",
+            PackageList::Lg => "LG",
+            PackageList::Microsoft => "Microsoft",
+            PackageList::Misc => "Misc",
+            PackageList::Motorola => "Motorola",
+            PackageList::Nokia => "Nokia",
+            PackageList::Oem => "Oem",
+            PackageList::OnePlus => "OnePlus",
+            PackageList::Pending => "Pending",
+            PackageList::Qualcomm => "Qualcomm",
+            PackageList::Samsung => "Samsung",
+            PackageList::Sony => "Sony",
+            PackageList::Xiaomi => "Xiaomi",
+            PackageList::Other(name) => name,
+        }
+    }
+}
+
+impl From<String> for Removal {
+    fn from(value: String) -> Self {
+        Removal::parse(&value)
+    }
+}
+
+impl From<Removal> for String {
+    fn from(value: Removal) -> Self {
+        value.label().to_string()
+    }
+}
+
+/// Strict, unlike [`Removal::parse`]: only the levels in [`Removal::ALL`],
+/// so a typo on the command line is an error rather than an empty filter.
+impl FromStr for Removal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Removal::parse(s) {
+            Removal::Other(_) => Err(unknown_value("removal level", s, &Removal::ALL)),
+            removal => Ok(removal),
+        }
+    }
+}
+
+impl fmt::Display for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl From<String> for PackageList {
+    fn from(value: String) -> Self {
+        PackageList::parse(&value)
+    }
+}
+
+impl From<PackageList> for String {
+    fn from(value: PackageList) -> Self {
+        value.label().to_string()
+    }
+}
+
+/// Strict, unlike [`PackageList::parse`]: only the lists UAD knows about.
+impl FromStr for PackageList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match PackageList::parse(s) {
+            PackageList::Other(_) => Err(unknown_value("list", s, &PackageList::KNOWN)),
+            list => Ok(list),
+        }
+    }
+}
+
+fn unknown_value(what: &str, value: &str, known: &[impl fmt::Display]) -> String {
+    let known: Vec<String> = known.iter().map(ToString::to_string).collect();
+    format!(
+        "unknown {} '{}' (expected one of: {})",
+        what,
+        value,
+        known.join(", ")
+    )
+}
+
+impl fmt::Display for PackageList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PackageInfo {
+    #[serde(default)]
     pub id: String,
-    pub list: Option<String>,
+    pub list: Option<PackageList>,
     pub description: Option<String>,
     pub dependencies: Option<Vec<String>>,
     #[serde(rename = "neededBy")]
     pub needed_by: Option<Vec<String>>,
     pub labels: Option<Vec<String>>,
-    pub removal: Option<String>,
+    pub removal: Option<Removal>,
+    /// Keys this version doesn't know about (e.g. `suggestions`), kept as-is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    #[serde(skip)]
+    pub source: ListSource,
 }
 
-pub fn load_uad_list() -> Vec<PackageInfo> {
+impl PackageInfo {
+    /// A bare entry for an installed package no list knows about.
+    pub fn unlisted(id: &str, description: String) -> Self {
+        Self {
+            id: id.to_string(),
+            list: None,
+            description: Some(description),
+            dependencies: None,
+            needed_by: None,
+            labels: None,
+            removal: None,
+            extra: Map::new(),
+            source: ListSource::Device,
+        }
+    }
+
+    /// The removal level, treating entries without one as `Unknown`.
+    pub fn removal_level(&self) -> &Removal {
+        self.removal.as_ref().unwrap_or(&Removal::Unknown)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(serde_json::Error),
+    InvalidFormat(String),
+    InvalidEntry { id: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            ConfigError::Parse(e) => write!(f, "Failed to parse package list: {}", e),
+            ConfigError::InvalidFormat(msg) => write!(f, "Invalid package list: {}", msg),
+            ConfigError::InvalidEntry { id, reason } => {
+                write!(f, "Invalid package list entry '{}': {}", id, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, e) => Some(e),
+            ConfigError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(e: serde_json::Error) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+fn parse_entry(id: Option<&str>, value: Value) -> Result<PackageInfo, ConfigError> {
+    let fallback_id = id
+        .map(str::to_string)
+        .or_else(|| value.get("id").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| "<unnamed>".to_string());
+    let mut info: PackageInfo =
+        serde_json::from_value(value).map_err(|e| ConfigError::InvalidEntry {
+            id: fallback_id,
+            reason: e.to_string(),
+        })?;
+    if let Some(id) = id {
+        info.id = id.to_string();
+    }
+    if info.id.is_empty() {
+        return Err(ConfigError::InvalidEntry {
+            id: "<unnamed>".to_string(),
+            reason: "missing package id".to_string(),
+        });
+    }
+    Ok(info)
+}
+
+/// Parses a debloat list in either of the two supported shapes: an object
+/// keyed by package id (the UAD format), or an array of entries that each
+/// carry their own `id`.
+pub fn parse_uad_list(json: &str) -> Result<Vec<PackageInfo>, ConfigError> {
+    match serde_json::from_str(json)? {
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(id, value)| parse_entry(Some(&id), value))
+            .collect(),
+        Value::Array(entries) => entries
+            .into_iter()
+            .map(|value| parse_entry(None, value))
+            .collect(),
+        _ => Err(ConfigError::InvalidFormat(
+            "expected an object keyed by package id or an array of packages".to_string(),
+        )),
+    }
+}
+
+pub fn load_uad_list() -> Result<Vec<PackageInfo>, ConfigError> {
     const UAD_JSON: &str = include_str!("../assets/data/uad_lists.json");
 
-    serde_json::from_str(UAD_JSON).expect("Failed to parse embedded uad_lists.json")
-}
\ No newline at end of file
+    parse_uad_list(UAD_JSON)
+}
+
+/// Directory scanned for extra `*.json` lists at startup.
+pub fn lists_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("purge").join("lists"))
+}
+
+pub fn load_list_file(path: &Path) -> Result<Vec<PackageInfo>, ConfigError> {
+    let json = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+    let mut packages = parse_uad_list(&json).map_err(|e| match e {
+        ConfigError::Parse(e) => ConfigError::InvalidFormat(format!("{}: {}", path.display(), e)),
+        other => other,
+    })?;
+    for info in &mut packages {
+        info.source = ListSource::File(path.to_path_buf());
+    }
+    Ok(packages)
+}
+
+/// Merges `overlay` into `base`. An entry whose id already exists replaces
+/// the earlier one wholesale (keeping its position); new ids are appended.
+pub fn merge_lists(base: &mut Vec<PackageInfo>, overlay: Vec<PackageInfo>) {
+    let mut index: HashMap<String, usize> = base
+        .iter()
+        .enumerate()
+        .map(|(i, info)| (info.id.clone(), i))
+        .collect();
+    for info in overlay {
+        match index.get(&info.id) {
+            Some(&i) => base[i] = info,
+            None => {
+                index.insert(info.id.clone(), base.len());
+                base.push(info);
+            }
+        }
+    }
+}
+
+/// Search and category filters shared by the GUI list and `purge list`.
+#[derive(Default)]
+pub struct PackageFilter<'a> {
+    pub search: &'a str,
+    pub list: Option<&'a PackageList>,
+    pub removal: Option<&'a Removal>,
+}
+
+impl PackageFilter<'_> {
+    pub fn matches(&self, info: &PackageInfo) -> bool {
+        (self.search.is_empty() || info.id.to_lowercase().contains(&self.search.to_lowercase()))
+            && self.list.is_none_or(|l| info.list.as_ref() == Some(l))
+            && self.removal.is_none_or(|r| info.removal_level() == r)
+    }
+}
+
+pub struct LoadedLists {
+    pub packages: Vec<PackageInfo>,
+    /// External lists that failed to load. They are skipped rather than
+    /// aborting startup.
+    pub errors: Vec<ConfigError>,
+}
+
+/// Loads the embedded list, then every `*.json` file in [`lists_dir`] in
+/// file name order. Later lists take precedence over earlier ones.
+pub fn load_all_lists() -> Result<LoadedLists, ConfigError> {
+    let mut packages = load_uad_list()?;
+    let mut errors = Vec::new();
+
+    if let Some(dir) = lists_dir() {
+        let mut files: Vec<PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        files.sort();
+        for path in files {
+            match load_list_file(&path) {
+                Ok(list) => merge_lists(&mut packages, list),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    Ok(LoadedLists { packages, errors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_uad_list_reads_the_object_shape() {
+        let json = r#"{
+            "com.foo": {
+                "list": "aosp",
+                "description": "Foo",
+                "neededBy": ["com.bar"],
+                "removal": "safe",
+                "suggestions": "keep"
+            }
+        }"#;
+        let packages = parse_uad_list(json).unwrap();
+        assert_eq!(packages.len(), 1);
+        let foo = &packages[0];
+        assert_eq!(foo.id, "com.foo");
+        assert_eq!(foo.list, Some(PackageList::Aosp));
+        assert_eq!(foo.removal_level(), &Removal::Safe);
+        assert_eq!(foo.needed_by.as_deref(), Some(&["com.bar".to_string()][..]));
+        assert_eq!(foo.extra.get("suggestions"), Some(&Value::from("keep")));
+    }
+
+    #[test]
+    fn parse_uad_list_reads_the_array_shape() {
+        let json = r#"[
+            { "id": "com.foo", "list": "Vendor X", "removal": "Risky" },
+            { "id": "com.bar" }
+        ]"#;
+        let packages = parse_uad_list(json).unwrap();
+        assert_eq!(
+            packages[0].list,
+            Some(PackageList::Other("Vendor X".to_string()))
+        );
+        assert_eq!(
+            packages[0].removal_level(),
+            &Removal::Other("Risky".to_string())
+        );
+        assert_eq!(packages[1].removal_level(), &Removal::Unknown);
+    }
+
+    #[test]
+    fn parse_uad_list_rejects_bad_input() {
+        assert!(matches!(
+            parse_uad_list("42"),
+            Err(ConfigError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_uad_list(r#"[{ "list": "Oem" }]"#),
+            Err(ConfigError::InvalidEntry { .. })
+        ));
+        match parse_uad_list(r#"{ "com.foo": { "neededBy": "com.bar" } }"#) {
+            Err(ConfigError::InvalidEntry { id, .. }) => assert_eq!(id, "com.foo"),
+            other => panic!("expected InvalidEntry, got {:?}", other),
+        }
+        assert!(matches!(parse_uad_list("{"), Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn from_str_only_accepts_known_values() {
+        assert_eq!("safe".parse::<Removal>(), Ok(Removal::Safe));
+        assert_eq!("OEM".parse::<PackageList>(), Ok(PackageList::Oem));
+        assert!("Saf".parse::<Removal>().unwrap_err().contains("Advanced"));
+        assert!("Oemm".parse::<PackageList>().unwrap_err().contains("Oem"));
+    }
+
+    #[test]
+    fn embedded_list_parses() {
+        assert!(!load_uad_list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_lists_replaces_in_place_and_appends() {
+        let mut base = parse_uad_list(
+            r#"[{ "id": "a", "removal": "Safe" }, { "id": "b", "removal": "Safe" }]"#,
+        )
+        .unwrap();
+        let overlay = parse_uad_list(
+            r#"[{ "id": "c", "removal": "Expert" }, { "id": "a", "removal": "Unsafe" }]"#,
+        )
+        .unwrap();
+        merge_lists(&mut base, overlay);
+        let ids: Vec<&str> = base.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(base[0].removal_level(), &Removal::Unsafe);
+    }
+}
@@ -0,0 +1,180 @@
+// src/deps.rs
+use crate::config::PackageInfo;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Reverse dependency index over the loaded lists: for each package, the
+/// packages that need it, merged from its `neededBy` and from every other
+/// entry's `dependencies`.
+#[derive(Default)]
+pub struct DependencyGraph {
+    dependents: HashMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn build(packages: &[PackageInfo]) -> Self {
+        let mut dependents: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for info in packages {
+            for needed_by in info.needed_by.iter().flatten() {
+                if *needed_by != info.id {
+                    dependents
+                        .entry(info.id.clone())
+                        .or_default()
+                        .insert(needed_by.clone());
+                }
+            }
+            for dependency in info.dependencies.iter().flatten() {
+                if *dependency != info.id {
+                    dependents
+                        .entry(dependency.clone())
+                        .or_default()
+                        .insert(info.id.clone());
+                }
+            }
+        }
+        Self { dependents }
+    }
+
+    pub fn dependents(&self, id: &str) -> impl Iterator<Item = &String> {
+        self.dependents.get(id).into_iter().flatten()
+    }
+
+    /// Installed packages that need `id` and are not part of `selected`.
+    pub fn remaining_dependents(
+        &self,
+        id: &str,
+        installed: &HashSet<String>,
+        selected: &HashSet<String>,
+    ) -> Vec<String> {
+        self.dependents(id)
+            .filter(|dep| installed.contains(*dep) && !selected.contains(*dep))
+            .cloned()
+            .collect()
+    }
+
+    /// Every installed package that transitively needs `id`.
+    pub fn installed_dependents_closure(
+        &self,
+        id: &str,
+        installed: &HashSet<String>,
+    ) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        let mut stack = vec![id.to_string()];
+        while let Some(current) = stack.pop() {
+            for dep in self.dependents(&current) {
+                if installed.contains(dep) && *dep != id && found.insert(dep.clone()) {
+                    stack.push(dep.clone());
+                }
+            }
+        }
+        found
+    }
+
+    /// Selected packages whose installed dependents would be left behind,
+    /// paired with those dependents.
+    pub fn unresolved(
+        &self,
+        selected: &HashSet<String>,
+        installed: &HashSet<String>,
+    ) -> Vec<(String, Vec<String>)> {
+        let mut unresolved: Vec<(String, Vec<String>)> = selected
+            .iter()
+            .map(|id| {
+                (
+                    id.clone(),
+                    self.remaining_dependents(id, installed, selected),
+                )
+            })
+            .filter(|(_, deps)| !deps.is_empty())
+            .collect();
+        unresolved.sort();
+        unresolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_uad_list;
+
+    fn graph(json: &str) -> DependencyGraph {
+        DependencyGraph::build(&parse_uad_list(json).unwrap())
+    }
+
+    fn set(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn build_merges_needed_by_with_reverse_dependencies() {
+        let graph = graph(
+            r#"{
+                "lib": { "neededBy": ["app1"] },
+                "app2": { "dependencies": ["lib"] }
+            }"#,
+        );
+        let dependents: Vec<&String> = graph.dependents("lib").collect();
+        assert_eq!(dependents, ["app1", "app2"]);
+        assert_eq!(graph.dependents("app1").count(), 0);
+    }
+
+    #[test]
+    fn closure_follows_dependents_of_dependents() {
+        let graph = graph(
+            r#"{
+                "core": { "neededBy": ["lib"] },
+                "lib": { "neededBy": ["app"] },
+                "app": {}
+            }"#,
+        );
+        let installed = set(&["core", "lib", "app"]);
+        let closure = graph.installed_dependents_closure("core", &installed);
+        assert_eq!(closure.into_iter().collect::<Vec<_>>(), ["app", "lib"]);
+    }
+
+    #[test]
+    fn cycles_and_self_references_terminate() {
+        let graph = graph(
+            r#"{
+                "a": { "neededBy": ["a", "b"] },
+                "b": { "neededBy": ["a"], "dependencies": ["b"] }
+            }"#,
+        );
+        assert_eq!(graph.dependents("b").collect::<Vec<_>>(), ["a"]);
+        let installed = set(&["a", "b"]);
+        let closure = graph.installed_dependents_closure("a", &installed);
+        assert_eq!(closure.into_iter().collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[test]
+    fn not_installed_dependents_are_ignored() {
+        let graph = graph(
+            r#"{
+                "core": { "neededBy": ["gone"] },
+                "gone": { "neededBy": ["app"] }
+            }"#,
+        );
+        let installed = set(&["core", "app"]);
+        assert!(graph
+            .installed_dependents_closure("core", &installed)
+            .is_empty());
+        assert!(graph.unresolved(&set(&["core"]), &installed).is_empty());
+    }
+
+    #[test]
+    fn unresolved_skips_dependents_in_the_selection() {
+        let graph = graph(
+            r#"{
+                "lib": { "neededBy": ["app1", "app2"] },
+                "other": { "neededBy": ["app1"] }
+            }"#,
+        );
+        let installed = set(&["lib", "other", "app1", "app2"]);
+        assert_eq!(
+            graph.unresolved(&set(&["lib", "other", "app1"]), &installed),
+            [("lib".to_string(), vec!["app2".to_string()])]
+        );
+        assert!(graph
+            .unresolved(&set(&["lib", "other", "app1", "app2"]), &installed)
+            .is_empty());
+    }
+}
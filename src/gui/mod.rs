@@ -5,14 +5,18 @@ mod theme;
 mod widgets;
 
 use crate::{
-    adb,
-    config::{load_uad_list, PackageInfo},
+    adb, backup,
+    config::{self, PackageInfo, PackageList, Removal},
+    deps::DependencyGraph,
+    journal, profile, wireless,
 };
 use eframe::egui;
 use egui_extras::RetainedImage;
-use std::collections::{BTreeSet, HashSet};
-use std::sync::mpsc;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::Duration;
 use theme::{apply_theme, Theme};
 
 #[derive(PartialEq)]
@@ -21,17 +25,116 @@ enum AppStatus {
     Busy,
     Error,
 }
+#[derive(PartialEq, Clone, Copy)]
+enum PackageView {
+    Installed,
+    Removed,
+    /// Installed packages that no loaded list covers.
+    Unlisted,
+    History,
+}
+/// Package commands carry the device serial and the id of the user to act on.
 enum AdbCommand {
-    Refresh,
-    Uninstall(Vec<String>),
-    Reboot,
+    ScanDevices,
+    Refresh(String, u32),
+    /// The flag backs up APKs before uninstalling.
+    Purge(String, u32, Vec<(String, adb::PurgeAction)>, bool),
+    /// The flag reinstalls uninstalled packages from their backups.
+    Restore(String, u32, Vec<(String, adb::PurgeAction)>, bool),
+    Reboot(String),
+    /// Reads `dumpsys` details for one package on one device.
+    FetchMetadata(String, u32, String),
+    /// Address and pairing code.
+    Pair(String, String),
+    Connect(String),
+    Disconnect(String),
+    /// Switches a USB device to TCP/IP on the default port.
+    EnableTcpip(String),
+    CheckServer,
+    RestartServer,
 }
 enum AdbResult {
-    RefreshSuccess(String, Vec<String>),
+    DevicesFound(Vec<adb::Device>),
+    /// Sent by the device watcher when a device attaches, detaches or
+    /// changes state.
+    DevicesChanged(Vec<adb::Device>),
+    RefreshSuccess(adb::DeviceSnapshot),
     RefreshFailure(String),
-    UninstallProgress(usize, usize),
-    UninstallFinished,
+    PurgeProgress(adb::PurgeAction, usize, usize),
+    /// Outcomes, then the packages a cancel left unprocessed.
+    PurgeFinished(Vec<(String, adb::PmOutcome)>, Vec<String>),
+    RestoreProgress(usize, usize),
+    RestoreFinished(Vec<(String, adb::PmOutcome)>, Vec<String>),
     RebootFinished,
+    MetadataLoaded(DeviceUserKey, String, Result<adb::PackageMetadata, String>),
+    Paired(Result<String, String>),
+    /// The connected address on success.
+    Connected(Result<String, String>),
+    Disconnected(String, Result<(), String>),
+    /// The address to connect to, when the device's Wi-Fi address is known.
+    TcpipEnabled(Result<Option<String>, String>),
+    ServerChecked(Result<adb::ServerStatus, String>),
+    ServerRestarted(Result<adb::ServerStatus, String>),
+}
+
+/// A device serial and the id of one of its users.
+type DeviceUserKey = (String, u32);
+
+/// `dumpsys` details per device and user, then per package.
+type MetadataCache = HashMap<DeviceUserKey, HashMap<String, Result<adb::PackageMetadata, String>>>;
+
+/// The fields of the wireless debugging window.
+#[derive(Default)]
+struct WirelessForm {
+    pair_address: String,
+    pair_code: String,
+    connect_address: String,
+}
+
+enum WirelessAction {
+    Pair,
+    Connect(String),
+    Forget(String),
+    Disconnect(String),
+    EnableTcpip(String),
+}
+
+struct ProfileReview {
+    name: String,
+    entries: Vec<(profile::ProfileEntry, profile::EntryStatus)>,
+}
+
+/// Typed before purging a selection that includes risky removal levels.
+const CONFIRMATION_WORD: &str = "PURGE";
+
+/// How long the device watcher waits before watching again after the adb
+/// server went away or couldn't be started.
+const WATCH_RETRY: Duration = Duration::from_secs(5);
+
+struct ReviewGroup {
+    removal: Removal,
+    /// `(package, action, needed_by)`, sorted by package.
+    packages: Vec<(String, adb::PurgeAction, Vec<String>)>,
+}
+
+/// Packages about to be purged, grouped by removal level.
+struct PurgeReview {
+    /// The batch to run once confirmed, each package with its own action.
+    packages: Vec<(String, adb::PurgeAction)>,
+    /// The action every package shares, `None` for a mixed profile.
+    action: Option<adb::PurgeAction>,
+    groups: Vec<ReviewGroup>,
+    /// Packages in the batch that installed packages outside it still need.
+    unresolved: Vec<(String, Vec<String>)>,
+    needs_typed_confirmation: bool,
+    typed: String,
+}
+
+struct OperationReport {
+    title: String,
+    outcomes: Vec<(String, adb::PmOutcome)>,
+    /// Packages the batch was cancelled before reaching.
+    skipped: Vec<String>,
 }
 
 struct TitleBarIcons {
@@ -72,19 +175,56 @@ pub struct DebloaterApp {
     theme: Theme,
     uad_packages: Vec<PackageInfo>,
     installed_packages: HashSet<String>,
+    /// What the Removed view lists: packages uninstalled for the user, and
+    /// hidden ones, which are just as invisible to it.
+    removed_packages: HashSet<String>,
+    hidden_packages: HashSet<String>,
+    disabled_packages: HashSet<String>,
+    suspended_packages: HashSet<String>,
+    package_details: HashMap<String, adb::InstalledPackage>,
+    unlisted_packages: Vec<PackageInfo>,
+    package_metadata: MetadataCache,
+    metadata_pending: HashSet<(DeviceUserKey, String)>,
+    purge_action: adb::PurgeAction,
+    backup_before_purge: bool,
+    restore_from_backup: bool,
+    view: PackageView,
     selected: HashSet<String>,
     active_selection: Option<PackageInfo>,
     status_message: String,
     device_name: String,
+    devices: Vec<adb::Device>,
+    /// Whether `devices` comes from a scan rather than startup.
+    devices_scanned: bool,
+    selected_serial: Option<String>,
+    users: Vec<adb::DeviceUser>,
+    selected_user: u32,
+    /// The users each package is installed for, on multi-user devices.
+    installed_for: HashMap<String, Vec<u32>>,
     search_query: String,
-    all_lists: Vec<String>,
-    filter_list: String,
-    all_removals: Vec<String>,
-    filter_removal: String,
+    all_lists: Vec<PackageList>,
+    /// `None` shows every list.
+    filter_list: Option<PackageList>,
+    all_removals: Vec<Removal>,
+    filter_removal: Option<Removal>,
     command_tx: mpsc::Sender<AdbCommand>,
     result_rx: mpsc::Receiver<AdbResult>,
     logo_texture: egui::TextureHandle,
     progress: f32,
+    /// A purge or restore batch is running on the worker.
+    batch_running: bool,
+    /// Checked by the worker before each package of a batch.
+    cancel_requested: Arc<AtomicBool>,
+    report: Option<OperationReport>,
+    profile_review: Option<ProfileReview>,
+    dependency_graph: DependencyGraph,
+    dependency_prompt: Option<(String, Vec<String>)>,
+    purge_review: Option<PurgeReview>,
+    wireless: Option<WirelessForm>,
+    remembered_endpoints: Vec<wireless::Endpoint>,
+    server: Option<adb::ServerStatus>,
+    history: Vec<journal::JournalEntry>,
+    selected_history: HashSet<usize>,
     app_status: AppStatus,
     title_bar_icons: TitleBarIcons,
 }
@@ -93,64 +233,149 @@ impl DebloaterApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let theme = Theme::default();
         apply_theme(&cc.egui_ctx, &theme);
-        let uad_packages = load_uad_list();
+        let (uad_packages, load_error) = match config::load_all_lists() {
+            Ok(loaded) => {
+                let error = loaded.errors.first().map(|e| {
+                    if loaded.errors.len() > 1 {
+                        format!("{} (and {} more)", e, loaded.errors.len() - 1)
+                    } else {
+                        e.to_string()
+                    }
+                });
+                (loaded.packages, error)
+            }
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
 
-        let all_lists: Vec<String> = ["All".to_string()]
-            .into_iter()
-            .chain(
-                uad_packages
-                    .iter()
-                    .filter_map(|p| p.list.clone())
-                    .collect::<BTreeSet<String>>(),
-            )
-            .collect();
-        let all_removals: Vec<String> = ["All".to_string()]
-            .into_iter()
-            .chain(
-                uad_packages
-                    .iter()
-                    .filter_map(|p| p.removal.clone())
-                    .collect::<BTreeSet<String>>(),
-            )
-            .collect();
+        let dependency_graph = DependencyGraph::build(&uad_packages);
+        let all_lists = filter_values(&uad_packages, |p| p.list.clone());
+        let all_removals = filter_values(&uad_packages, |p| Some(p.removal_level().clone()));
+
+        // A bad fake device fixture is reported like a bad list; the app
+        // falls back to real devices.
+        let (backend, load_error): (Arc<dyn adb::DeviceBackend>, _) = match adb::backend(None) {
+            Ok(backend) => (Arc::from(backend), load_error),
+            Err(e) => (Arc::new(adb::AdbBackend), Some(e)),
+        };
 
         let (command_tx, command_rx) = mpsc::channel();
         let (result_tx, result_rx) = mpsc::channel();
+        let cancel_requested = Arc::new(AtomicBool::new(false));
+        let cancel = cancel_requested.clone();
+
+        // Watches for hotplug on its own thread, since watching blocks. It
+        // stops once the app is gone and the send fails.
+        let watcher = backend.clone();
+        let watch_tx = result_tx.clone();
+        thread::spawn(move || {
+            let mut on_change = |devices| watch_tx.send(AdbResult::DevicesChanged(devices)).is_ok();
+            while watcher.watch_devices(&mut on_change).is_err() {
+                thread::sleep(WATCH_RETRY);
+            }
+        });
 
         thread::spawn(move || {
             while let Ok(command) = command_rx.recv() {
                 let result = match command {
-                    AdbCommand::Refresh => match adb::detect_device() {
-                        Ok(_) => match adb::get_device_model() {
-                            Ok(model) => match adb::list_packages() {
-                                Ok(packages) => AdbResult::RefreshSuccess(model, packages),
-                                Err(e) => AdbResult::RefreshFailure(e),
-                            },
-                            Err(e) => AdbResult::RefreshFailure(e),
-                        },
+                    AdbCommand::ScanDevices => match backend.list_devices() {
+                        Ok(devices) => AdbResult::DevicesFound(devices),
+                        Err(e) => AdbResult::RefreshFailure(e),
+                    },
+                    AdbCommand::Refresh(serial, user) => match backend.load_device(&serial, user) {
+                        Ok(snapshot) => AdbResult::RefreshSuccess(snapshot),
                         Err(e) => AdbResult::RefreshFailure(e),
                     },
-                    AdbCommand::Uninstall(packages) => {
+                    AdbCommand::Purge(serial, user, packages, backup) => {
+                        let total = packages.len();
+                        let tx = result_tx.clone();
+                        let session = journal::new_session();
+                        let mut outcomes = Vec::with_capacity(total);
+                        for (i, (pkg, action)) in packages.iter().enumerate() {
+                            if cancel.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let outcome = if backup {
+                                backup::purge_with_backup(
+                                    backend.as_ref(),
+                                    &serial,
+                                    user,
+                                    pkg,
+                                    *action,
+                                )
+                            } else {
+                                backend.purge(&serial, user, pkg, *action)
+                            };
+                            journal::record(session, &serial, user, pkg, *action, false, &outcome);
+                            outcomes.push((pkg.clone(), outcome));
+                            if tx
+                                .send(AdbResult::PurgeProgress(*action, i + 1, total))
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                        let skipped = unprocessed(&packages, outcomes.len());
+                        AdbResult::PurgeFinished(outcomes, skipped)
+                    }
+                    AdbCommand::Restore(serial, user, packages, from_backup) => {
                         let total = packages.len();
                         let tx = result_tx.clone();
-                        for (i, pkg) in packages.iter().enumerate() {
-                            adb::uninstall(pkg);
-                            if tx.send(AdbResult::UninstallProgress(i + 1, total)).is_err() {
+                        let session = journal::new_session();
+                        let mut outcomes = Vec::with_capacity(total);
+                        for (i, (pkg, action)) in packages.iter().enumerate() {
+                            if cancel.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let outcome = if from_backup {
+                                backup::revert_preferring_backup(
+                                    backend.as_ref(),
+                                    &serial,
+                                    user,
+                                    pkg,
+                                    *action,
+                                )
+                            } else {
+                                backend.revert(&serial, user, pkg, *action)
+                            };
+                            journal::record(session, &serial, user, pkg, *action, true, &outcome);
+                            outcomes.push((pkg.clone(), outcome));
+                            if tx.send(AdbResult::RestoreProgress(i + 1, total)).is_err() {
                                 break;
                             }
                         }
-                        AdbResult::UninstallFinished
+                        let skipped = unprocessed(&packages, outcomes.len());
+                        AdbResult::RestoreFinished(outcomes, skipped)
                     }
-                    AdbCommand::Reboot => {
-                        adb::reboot_device();
+                    AdbCommand::Reboot(serial) => {
+                        backend.reboot(&serial);
                         AdbResult::RebootFinished
                     }
+                    AdbCommand::FetchMetadata(serial, user, package) => {
+                        let metadata = backend.package_metadata(&serial, user, &package);
+                        AdbResult::MetadataLoaded((serial, user), package, metadata)
+                    }
+                    AdbCommand::Pair(address, code) => {
+                        AdbResult::Paired(backend.pair(&address, &code))
+                    }
+                    AdbCommand::Connect(address) => AdbResult::Connected(backend.connect(&address)),
+                    AdbCommand::Disconnect(address) => {
+                        let result = backend.disconnect(Some(&address));
+                        AdbResult::Disconnected(address, result)
+                    }
+                    AdbCommand::EnableTcpip(serial) => AdbResult::TcpipEnabled(
+                        backend.enable_tcpip(&serial, adb::DEFAULT_TCPIP_PORT),
+                    ),
+                    AdbCommand::CheckServer => AdbResult::ServerChecked(backend.server_status()),
+                    AdbCommand::RestartServer => {
+                        AdbResult::ServerRestarted(backend.restart_server())
+                    }
                 };
                 if result_tx.send(result).is_err() {
                     break;
                 }
             }
         });
+        let _ = command_tx.send(AdbCommand::CheckServer);
 
         let image = image::load_from_memory(include_bytes!("../../assets/img/logo.png")).unwrap();
         let color_image = egui::ColorImage::from_rgba_unmultiplied(
@@ -170,15 +395,52 @@ impl DebloaterApp {
             result_rx,
             logo_texture,
             installed_packages: HashSet::new(),
+            removed_packages: HashSet::new(),
+            hidden_packages: HashSet::new(),
+            disabled_packages: HashSet::new(),
+            suspended_packages: HashSet::new(),
+            package_details: HashMap::new(),
+            unlisted_packages: Vec::new(),
+            package_metadata: HashMap::new(),
+            metadata_pending: HashSet::new(),
+            purge_action: adb::PurgeAction::Uninstall,
+            backup_before_purge: false,
+            restore_from_backup: false,
+            view: PackageView::Installed,
             selected: HashSet::new(),
             active_selection: None,
-            status_message: "Welcome! Connect your device to begin.".to_string(),
+            status_message: match &load_error {
+                Some(e) => format!("Error: {}", e),
+                None => "Welcome! Connect your device to begin.".to_string(),
+            },
             device_name: "No Device Connected".to_string(),
+            devices: Vec::new(),
+            devices_scanned: false,
+            selected_serial: None,
+            users: Vec::new(),
+            selected_user: adb::PRIMARY_USER,
+            installed_for: HashMap::new(),
             search_query: String::new(),
-            filter_list: "All".to_string(),
-            filter_removal: "All".to_string(),
+            filter_list: None,
+            filter_removal: None,
             progress: 0.0,
-            app_status: AppStatus::Ready,
+            batch_running: false,
+            cancel_requested,
+            report: None,
+            profile_review: None,
+            dependency_graph,
+            dependency_prompt: None,
+            purge_review: None,
+            wireless: None,
+            remembered_endpoints: Vec::new(),
+            server: None,
+            history: Vec::new(),
+            selected_history: HashSet::new(),
+            app_status: if load_error.is_some() {
+                AppStatus::Error
+            } else {
+                AppStatus::Ready
+            },
             title_bar_icons: TitleBarIcons::new(),
         }
     }
@@ -186,41 +448,1335 @@ impl DebloaterApp {
     fn handle_adb_results(&mut self) {
         if let Ok(result) = self.result_rx.try_recv() {
             match result {
-                AdbResult::RefreshSuccess(device, packages) => {
-                    self.device_name = device;
-                    self.installed_packages = packages.into_iter().collect();
+                AdbResult::DevicesFound(devices) => self.apply_devices(devices, true),
+                AdbResult::DevicesChanged(devices) => {
+                    if devices != self.devices {
+                        self.apply_devices(devices, false);
+                    }
+                }
+                AdbResult::RefreshSuccess(snapshot) => {
+                    self.device_name = snapshot.model;
+                    self.selected_user = snapshot.user;
+                    self.users = snapshot.users;
+                    self.installed_for = snapshot.installed_for;
+                    self.installed_packages = snapshot.installed.into_iter().collect();
+                    self.hidden_packages = snapshot.hidden.into_iter().collect();
+                    self.removed_packages = snapshot.removed.into_iter().collect();
+                    self.removed_packages
+                        .extend(self.hidden_packages.iter().cloned());
+                    self.disabled_packages = snapshot.disabled.into_iter().collect();
+                    self.suspended_packages = snapshot.suspended.into_iter().collect();
+                    self.package_details = snapshot
+                        .details
+                        .into_iter()
+                        .map(|d| (d.id.clone(), d))
+                        .collect();
+                    // Lookups that failed get another try after a reload.
+                    for cache in self.package_metadata.values_mut() {
+                        cache.retain(|_, metadata| metadata.is_ok());
+                    }
+                    self.rebuild_unlisted();
                     self.status_message = "Ready.".to_string();
                     self.app_status = AppStatus::Ready;
                 }
                 AdbResult::RefreshFailure(e) => {
                     self.device_name = "No Device Connected".to_string();
-                    self.installed_packages.clear();
+                    self.clear_device_packages();
                     self.status_message = format!("Error: {}", e);
                     self.app_status = AppStatus::Error;
                 }
-                AdbResult::UninstallProgress(current, total) => {
+                AdbResult::PurgeProgress(action, current, total) => {
                     self.progress = current as f32 / total as f32;
-                    self.status_message = format!("Purging {} of {}...", current, total);
+                    if !self.cancel_requested.load(Ordering::Relaxed) {
+                        self.status_message =
+                            format!("{} {} of {}...", action.progress_verb(), current, total);
+                    }
                 }
-                AdbResult::UninstallFinished => {
-                    self.status_message = "Purge complete. Refreshing...".to_string();
-                    let _ = self.command_tx.send(AdbCommand::Refresh);
-                    self.selected.clear();
-                    self.active_selection = None;
-                    self.progress = 0.0;
+                AdbResult::PurgeFinished(outcomes, skipped) => {
+                    self.finish_batch("Purge", outcomes, skipped);
+                }
+                AdbResult::RestoreProgress(current, total) => {
+                    self.progress = current as f32 / total as f32;
+                    if !self.cancel_requested.load(Ordering::Relaxed) {
+                        self.status_message = format!("Restoring {} of {}...", current, total);
+                    }
+                }
+                AdbResult::RestoreFinished(outcomes, skipped) => {
+                    self.finish_batch("Restore", outcomes, skipped);
                 }
                 AdbResult::RebootFinished => {
                     self.status_message = "Reboot command sent.".to_string();
                     self.app_status = AppStatus::Ready;
                 }
+                AdbResult::Paired(Ok(message)) => {
+                    self.status_message = format!(
+                        "{}. Connect using the address shown under Wireless debugging.",
+                        message
+                    );
+                    self.app_status = AppStatus::Ready;
+                    if let Some(form) = &mut self.wireless {
+                        form.pair_code.clear();
+                        if form.connect_address.is_empty() {
+                            if let Some((host, _)) = form.pair_address.rsplit_once(':') {
+                                form.connect_address = format!("{}:", host);
+                            }
+                        }
+                    }
+                    let _ = self.command_tx.send(AdbCommand::ScanDevices);
+                }
+                AdbResult::Connected(Ok(address)) => {
+                    if let Err(e) = wireless::remember(&address) {
+                        self.status_message = format!("Error: {}", e);
+                    } else {
+                        self.status_message = format!("Connected to {}.", address);
+                    }
+                    self.reload_endpoints();
+                    let _ = self.command_tx.send(AdbCommand::ScanDevices);
+                }
+                AdbResult::Disconnected(address, Ok(())) => {
+                    self.status_message = format!("Disconnected {}.", address);
+                    let _ = self.command_tx.send(AdbCommand::ScanDevices);
+                }
+                AdbResult::TcpipEnabled(Ok(address)) => {
+                    self.status_message = match &address {
+                        Some(address) => {
+                            format!(
+                                "TCP/IP enabled. Unplug the cable and connect to {}.",
+                                address
+                            )
+                        }
+                        None => format!(
+                            "TCP/IP enabled. Unplug the cable and connect to the device's Wi-Fi \
+                             address on port {}.",
+                            adb::DEFAULT_TCPIP_PORT
+                        ),
+                    };
+                    self.app_status = AppStatus::Ready;
+                    if let (Some(form), Some(address)) = (&mut self.wireless, address) {
+                        form.connect_address = address;
+                    }
+                }
+                AdbResult::Paired(Err(e))
+                | AdbResult::Connected(Err(e))
+                | AdbResult::Disconnected(_, Err(e))
+                | AdbResult::TcpipEnabled(Err(e)) => {
+                    self.status_message = format!("Error: {}", e);
+                    self.app_status = AppStatus::Error;
+                }
+                AdbResult::ServerChecked(status) => match status {
+                    Ok(status) => self.server = Some(status),
+                    Err(e) => {
+                        self.server = None;
+                        self.status_message = format!("Error: {}", e);
+                        self.app_status = AppStatus::Error;
+                    }
+                },
+                AdbResult::ServerRestarted(Ok(status)) => {
+                    self.server = Some(status);
+                    self.status_message =
+                        "ADB server restarted. Scanning for devices...".to_string();
+                    let _ = self.command_tx.send(AdbCommand::ScanDevices);
+                }
+                AdbResult::ServerRestarted(Err(e)) => {
+                    self.status_message = format!("Error: {}", e);
+                    self.app_status = AppStatus::Error;
+                    let _ = self.command_tx.send(AdbCommand::CheckServer);
+                }
+                AdbResult::MetadataLoaded(key, package, metadata) => {
+                    self.metadata_pending
+                        .remove(&(key.clone(), package.clone()));
+                    self.package_metadata
+                        .entry(key)
+                        .or_default()
+                        .insert(package, metadata);
+                }
+            }
+        }
+    }
+
+    /// Takes a new device listing, keeping the selected device while it
+    /// stays usable and otherwise falling back to the first authorized one.
+    /// Packages are reloaded when the selection changes, or always when
+    /// `rescan` is set by an explicit refresh.
+    fn apply_devices(&mut self, devices: Vec<adb::Device>, rescan: bool) {
+        let previous = self.selected_serial.clone();
+        let still_present = self.selected_serial.as_ref().is_some_and(|serial| {
+            devices
+                .iter()
+                .any(|d| &d.serial == serial && d.is_authorized())
+        });
+        if !still_present {
+            self.selected_serial = devices
+                .iter()
+                .find(|d| d.is_authorized())
+                .map(|d| d.serial.clone());
+            self.users.clear();
+        }
+        // Fall back to the primary user if the selected one is gone, e.g. a
+        // removed work profile.
+        if !self.users.iter().any(|u| u.id == self.selected_user) {
+            self.selected_user = adb::PRIMARY_USER;
+        }
+        let problem = match devices.first() {
+            Some(device) => format!("{} is {}.", device.serial, device.state),
+            None => "No device found.".to_string(),
+        };
+        self.devices = devices;
+        self.devices_scanned = true;
+
+        let selection_changed = self.selected_serial != previous;
+        if selection_changed {
+            // Checked packages and open prompts belong to the old device.
+            self.selected.clear();
+            self.active_selection = None;
+            self.selected_history.clear();
+            self.purge_review = None;
+            self.dependency_prompt = None;
+        }
+        match &self.selected_serial {
+            Some(_) if !rescan && !selection_changed => {}
+            Some(serial) => {
+                self.app_status = AppStatus::Busy;
+                self.status_message = "Loading packages...".to_string();
+                let _ = self
+                    .command_tx
+                    .send(AdbCommand::Refresh(serial.clone(), self.selected_user));
+            }
+            None if !rescan && !selection_changed => {}
+            None => {
+                self.device_name = "No Device Connected".to_string();
+                self.clear_device_packages();
+                self.status_message = format!("Error: {}", problem);
+                self.app_status = AppStatus::Error;
+            }
+        }
+    }
+
+    fn reload_endpoints(&mut self) {
+        match wireless::load() {
+            Ok(endpoints) => self.remembered_endpoints = endpoints,
+            Err(e) => self.status_message = format!("Error: {}", e),
+        }
+    }
+
+    fn draw_wireless_window(&mut self, ctx: &egui::Context) {
+        let Some(form) = &mut self.wireless else {
+            return;
+        };
+        let busy = self.app_status == AppStatus::Busy;
+        let usb_serial = self
+            .devices
+            .iter()
+            .find(|d| Some(&d.serial) == self.selected_serial.as_ref())
+            .filter(|d| d.is_authorized() && !d.is_wireless())
+            .map(|d| d.serial.clone());
+        let mut action = None;
+        let mut open = true;
+        egui::Window::new("Wireless Debugging")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.set_enabled(!busy);
+                ui.label(egui::RichText::new("Pair (Android 11+)").strong());
+                ui.label(
+                    egui::RichText::new(
+                        "On the device, open Developer options › Wireless debugging › \
+                         Pair device with pairing code.",
+                    )
+                    .color(self.theme.on_surface_variant),
+                );
+                egui::Grid::new("wireless_pair")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Address");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut form.pair_address)
+                                .hint_text("192.168.1.20:37099"),
+                        );
+                        ui.end_row();
+                        ui.label("Code");
+                        ui.add(egui::TextEdit::singleline(&mut form.pair_code).hint_text("123456"));
+                        ui.end_row();
+                    });
+                let can_pair =
+                    !form.pair_address.trim().is_empty() && !form.pair_code.trim().is_empty();
+                if ui
+                    .add_enabled(can_pair, egui::Button::new("Pair"))
+                    .clicked()
+                {
+                    action = Some(WirelessAction::Pair);
+                }
+
+                ui.separator();
+                ui.label(egui::RichText::new("Connect").strong());
+                ui.label(
+                    egui::RichText::new(
+                        "Use the IP address & port shown under Wireless debugging, or port \
+                         5555 after switching an older device to TCP/IP.",
+                    )
+                    .color(self.theme.on_surface_variant),
+                );
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut form.connect_address)
+                            .hint_text("192.168.1.20:41235"),
+                    );
+                    if ui
+                        .add_enabled(
+                            !form.connect_address.trim().is_empty(),
+                            egui::Button::new("Connect"),
+                        )
+                        .clicked()
+                    {
+                        action = Some(WirelessAction::Connect(form.connect_address.clone()));
+                    }
+                });
+                for endpoint in &self.remembered_endpoints {
+                    ui.horizontal(|ui| {
+                        ui.label(&endpoint.address);
+                        if ui.small_button("Connect").clicked() {
+                            action = Some(WirelessAction::Connect(endpoint.address.clone()));
+                        }
+                        if ui.small_button("Forget").clicked() {
+                            action = Some(WirelessAction::Forget(endpoint.address.clone()));
+                        }
+                    });
+                }
+
+                let connected: Vec<&adb::Device> =
+                    self.devices.iter().filter(|d| d.is_wireless()).collect();
+                if !connected.is_empty() {
+                    ui.separator();
+                    ui.label(egui::RichText::new("Connected over Wi-Fi").strong());
+                    for device in connected {
+                        ui.horizontal(|ui| {
+                            ui.label(device.display_name());
+                            if ui.small_button("Disconnect").clicked() {
+                                action = Some(WirelessAction::Disconnect(device.serial.clone()));
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                ui.label(egui::RichText::new("Older devices").strong());
+                ui.label(
+                    egui::RichText::new(
+                        "With the device on USB, restart adb in TCP/IP mode, then unplug it \
+                         and connect.",
+                    )
+                    .color(self.theme.on_surface_variant),
+                );
+                if let Some(serial) = &usb_serial {
+                    if ui
+                        .button(format!(
+                            "Switch to TCP/IP (port {})",
+                            adb::DEFAULT_TCPIP_PORT
+                        ))
+                        .clicked()
+                    {
+                        action = Some(WirelessAction::EnableTcpip(serial.clone()));
+                    }
+                }
+            });
+
+        let command = match action {
+            Some(WirelessAction::Pair) => {
+                self.status_message = "Pairing...".to_string();
+                Some(AdbCommand::Pair(
+                    form.pair_address.trim().to_string(),
+                    form.pair_code.trim().to_string(),
+                ))
+            }
+            Some(WirelessAction::Connect(address)) => {
+                self.status_message = format!("Connecting to {}...", address.trim());
+                Some(AdbCommand::Connect(address.trim().to_string()))
+            }
+            Some(WirelessAction::Disconnect(address)) => {
+                self.status_message = format!("Disconnecting {}...", address);
+                Some(AdbCommand::Disconnect(address))
+            }
+            Some(WirelessAction::EnableTcpip(serial)) => {
+                self.status_message = "Switching to TCP/IP...".to_string();
+                Some(AdbCommand::EnableTcpip(serial))
+            }
+            Some(WirelessAction::Forget(address)) => {
+                if let Err(e) = wireless::forget(&address) {
+                    self.status_message = format!("Error: {}", e);
+                }
+                self.reload_endpoints();
+                None
+            }
+            None => None,
+        };
+        if let Some(command) = command {
+            self.app_status = AppStatus::Busy;
+            self.command_tx.send(command).unwrap();
+        }
+        if !open {
+            self.wireless = None;
+        }
+    }
+
+    /// Sends a purge or restore batch to the worker, which can be cancelled
+    /// between packages.
+    fn start_batch(&mut self, command: AdbCommand) {
+        self.cancel_requested.store(false, Ordering::Relaxed);
+        self.batch_running = true;
+        self.app_status = AppStatus::Busy;
+        self.command_tx.send(command).unwrap();
+    }
+
+    fn finish_batch(
+        &mut self,
+        operation: &str,
+        outcomes: Vec<(String, adb::PmOutcome)>,
+        skipped: Vec<String>,
+    ) {
+        self.forget_metadata(&outcomes);
+        self.batch_running = false;
+        self.status_message = if skipped.is_empty() {
+            format!("{} complete. Refreshing...", operation)
+        } else {
+            format!(
+                "{} cancelled after {} of {} packages. Refreshing...",
+                operation,
+                outcomes.len(),
+                outcomes.len() + skipped.len()
+            )
+        };
+        // Keep what wasn't reached selected so the batch can be resumed.
+        self.selected = if self.view == PackageView::History {
+            HashSet::new()
+        } else {
+            skipped.iter().cloned().collect()
+        };
+        self.report = Some(OperationReport {
+            title: if skipped.is_empty() {
+                format!("{} Summary", operation)
+            } else {
+                format!("{} Cancelled", operation)
+            },
+            outcomes,
+            skipped,
+        });
+        let _ = self.command_tx.send(AdbCommand::ScanDevices);
+        self.active_selection = None;
+        self.progress = 0.0;
+        self.reload_history();
+    }
+
+    /// Fetches `dumpsys` details for `package` unless they are cached or
+    /// already on their way.
+    fn request_metadata(&mut self, package: &str) {
+        let Some(device_user) = self.device_user() else {
+            return;
+        };
+        let cached = self
+            .package_metadata
+            .get(&device_user)
+            .is_some_and(|m| m.contains_key(package));
+        let key = (device_user, package.to_string());
+        if cached || self.metadata_pending.contains(&key) {
+            return;
+        }
+        self.metadata_pending.insert(key.clone());
+        let ((serial, user), package) = key;
+        let _ = self
+            .command_tx
+            .send(AdbCommand::FetchMetadata(serial, user, package));
+    }
+
+    /// Drops cached details for packages an operation just changed.
+    fn forget_metadata(&mut self, outcomes: &[(String, adb::PmOutcome)]) {
+        let Some(cache) = self
+            .device_user()
+            .and_then(|key| self.package_metadata.get_mut(&key))
+        else {
+            return;
+        };
+        for (package, _) in outcomes {
+            cache.remove(package);
+        }
+    }
+
+    fn draw_report_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        let failures: Vec<&(String, adb::PmOutcome)> = report
+            .outcomes
+            .iter()
+            .filter(|(_, outcome)| !outcome.is_success())
+            .collect();
+        let succeeded = report.outcomes.len() - failures.len();
+
+        let mut open = true;
+        let mut close_clicked = false;
+        egui::Window::new(&report.title)
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if report.skipped.is_empty() {
+                    ui.label(format!(
+                        "{} succeeded, {} failed.",
+                        succeeded,
+                        failures.len()
+                    ));
+                } else {
+                    ui.label(format!(
+                        "{} succeeded, {} failed, {} not processed.",
+                        succeeded,
+                        failures.len(),
+                        report.skipped.len()
+                    ));
+                }
+                if !failures.is_empty() {
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("report_failures")
+                                .num_columns(2)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for (package, outcome) in &failures {
+                                        ui.label(egui::RichText::new(package).strong());
+                                        ui.label(
+                                            egui::RichText::new(outcome.message())
+                                                .color(self.theme.status_err),
+                                        );
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+                if !report.skipped.is_empty() {
+                    ui.separator();
+                    ui.label(egui::RichText::new("Not processed").strong());
+                    egui::ScrollArea::vertical()
+                        .id_source("report_skipped")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for package in &report.skipped {
+                                ui.label(
+                                    egui::RichText::new(package)
+                                        .color(self.theme.on_surface_variant),
+                                );
+                            }
+                        });
+                }
+                ui.add_space(6.0);
+                if ui.button("Close").clicked() {
+                    close_clicked = true;
+                }
+            });
+        if !open || close_clicked {
+            self.report = None;
+        }
+    }
+
+    fn import_list(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_title("Load Debloat List")
+            .pick_file()
+        else {
+            return;
+        };
+        match config::load_list_file(&path) {
+            Ok(packages) => {
+                let count = packages.len();
+                config::merge_lists(&mut self.uad_packages, packages);
+                self.dependency_graph = DependencyGraph::build(&self.uad_packages);
+                self.all_lists = filter_values(&self.uad_packages, |p| p.list.clone());
+                self.all_removals =
+                    filter_values(&self.uad_packages, |p| Some(p.removal_level().clone()));
+                self.rebuild_unlisted();
+                self.active_selection = None;
+                self.status_message = format!("Loaded {} packages from list.", count);
+            }
+            Err(e) => {
+                self.status_message = format!("Error: {}", e);
+                self.app_status = AppStatus::Error;
             }
         }
     }
 
+    fn save_profile(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Profile", &["json", "toml"])
+            .set_title("Save Profile")
+            .set_file_name("profile.json")
+            .save_file()
+        else {
+            return;
+        };
+        let name = path.file_stem().map_or_else(
+            || "Profile".to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        );
+        let mut ids: Vec<&String> = self.selected.iter().collect();
+        ids.sort();
+        let profile = profile::Profile {
+            name,
+            packages: ids
+                .into_iter()
+                .map(|id| profile::ProfileEntry {
+                    id: id.clone(),
+                    action: self.purge_action,
+                    notes: None,
+                })
+                .collect(),
+        };
+        self.status_message = match profile::save(&profile, &path) {
+            Ok(()) => format!("Saved profile '{}'.", profile.name),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    fn load_profile(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Profile", &["json", "toml"])
+            .set_title("Load Profile")
+            .pick_file()
+        else {
+            return;
+        };
+        match profile::load(&path) {
+            Ok(loaded) => {
+                let known: HashSet<&str> =
+                    self.uad_packages.iter().map(|p| p.id.as_str()).collect();
+                let entries = profile::diff(
+                    &loaded,
+                    &known,
+                    &self.installed_packages,
+                    &self.removed_packages,
+                );
+                self.profile_review = Some(ProfileReview {
+                    name: loaded.name,
+                    entries,
+                });
+            }
+            Err(e) => {
+                self.status_message = format!("Error: {}", e);
+            }
+        }
+    }
+
+    fn draw_profile_window(&mut self, ctx: &egui::Context) {
+        let Some(review) = &self.profile_review else {
+            return;
+        };
+        let installed: Vec<&profile::ProfileEntry> = review
+            .entries
+            .iter()
+            .filter(|(_, status)| *status == profile::EntryStatus::Installed)
+            .map(|(entry, _)| entry)
+            .collect();
+
+        let mut open = true;
+        let mut close = false;
+        let mut apply = false;
+        egui::Window::new(format!("Profile: {}", review.name))
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} entries, {} installed on this device.",
+                    review.entries.len(),
+                    installed.len()
+                ));
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(350.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("profile_entries")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (entry, status) in &review.entries {
+                                    let color = match status {
+                                        profile::EntryStatus::Installed => self.theme.status_ok,
+                                        profile::EntryStatus::Removed => self.theme.status_neutral,
+                                        profile::EntryStatus::Missing => self.theme.status_warn,
+                                        profile::EntryStatus::Unknown => self.theme.status_err,
+                                    };
+                                    ui.label(egui::RichText::new(&entry.id).strong());
+                                    ui.label(entry.action.label());
+                                    ui.label(egui::RichText::new(status.label()).color(color));
+                                    ui.label(entry.notes.as_deref().unwrap_or(""));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    let can_act = !installed.is_empty() && !self.is_busy();
+                    if ui
+                        .add_enabled(
+                            can_act && self.selected_serial.is_some(),
+                            egui::Button::new("🔥 Apply Profile"),
+                        )
+                        .clicked()
+                    {
+                        apply = true;
+                    }
+                    if ui.button("Close").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if apply {
+            let packages = installed.iter().map(|e| (e.id.clone(), e.action)).collect();
+            self.purge_review = Some(self.review_purge(packages));
+        }
+        if !open || close || apply {
+            self.profile_review = None;
+        }
+    }
+
+    /// Restores the selection of the Removed or History view. Purges go
+    /// through [`Self::review_purge`] instead.
+    fn run_restore(&mut self) {
+        let Some(serial) = self.selected_serial.clone() else {
+            return;
+        };
+        let packages: Vec<String> = self.selected.iter().cloned().collect();
+        let command = match self.view {
+            PackageView::Installed | PackageView::Unlisted => return,
+            PackageView::Removed => AdbCommand::Restore(
+                serial,
+                self.selected_user,
+                packages
+                    .into_iter()
+                    .map(|pkg| {
+                        let action = self
+                            .package_state(&pkg)
+                            .purge_action()
+                            .unwrap_or(adb::PurgeAction::Uninstall);
+                        (pkg, action)
+                    })
+                    .collect(),
+                self.restore_from_backup,
+            ),
+            PackageView::History => {
+                // Undo newest first so a session unwinds in reverse.
+                let mut indices: Vec<usize> = self.selected_history.iter().copied().collect();
+                indices.sort_unstable_by_key(|&i| std::cmp::Reverse(i));
+                AdbCommand::Restore(
+                    serial,
+                    self.selected_user,
+                    indices
+                        .into_iter()
+                        .map(|i| {
+                            let entry = &self.history[i];
+                            (entry.package.clone(), entry.action)
+                        })
+                        .collect(),
+                    self.restore_from_backup,
+                )
+            }
+        };
+        self.start_batch(command);
+    }
+
+    fn check_dependents(&mut self, id: &str) {
+        let closure = self
+            .dependency_graph
+            .installed_dependents_closure(id, &self.installed_packages);
+        let remaining: Vec<String> = closure
+            .into_iter()
+            .filter(|dep| !self.selected.contains(dep))
+            .collect();
+        if !remaining.is_empty() {
+            self.dependency_prompt = Some((id.to_string(), remaining));
+        }
+    }
+
+    fn draw_dependency_prompt(&mut self, ctx: &egui::Context) {
+        let Some((id, dependents)) = &self.dependency_prompt else {
+            return;
+        };
+        let mut select_all = false;
+        let mut keep = false;
+        let mut cancel = false;
+        egui::Window::new("Package Is Still Needed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} is needed by these installed packages, which may break without it:",
+                    id
+                ));
+                ui.add_space(4.0);
+                for dep in dependents {
+                    ui.label(
+                        egui::RichText::new(dep)
+                            .strong()
+                            .color(self.theme.status_warn),
+                    );
+                }
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    select_all = ui.button("Select Them Too").clicked();
+                    keep = ui.button("Keep Selection Only").clicked();
+                    cancel = ui.button("Deselect").clicked();
+                });
+            });
+        if select_all {
+            self.selected.extend(dependents.iter().cloned());
+        }
+        if cancel {
+            self.selected.remove(id);
+        }
+        if select_all || keep || cancel {
+            self.dependency_prompt = None;
+        }
+    }
+
+    /// The review shown before purging `packages`, whether they come from
+    /// the selection or from a profile.
+    fn review_purge(&self, packages: Vec<(String, adb::PurgeAction)>) -> PurgeReview {
+        let mut groups: Vec<ReviewGroup> = Vec::new();
+        for (id, action) in &packages {
+            let info = self.uad_packages.iter().find(|p| &p.id == id);
+            let removal = info.map_or(Removal::Unknown, |p| p.removal_level().clone());
+            let needed_by = info.and_then(|p| p.needed_by.clone()).unwrap_or_default();
+            let row = (id.clone(), *action, needed_by);
+            match groups.iter_mut().find(|g| g.removal == removal) {
+                Some(group) => group.packages.push(row),
+                None => groups.push(ReviewGroup {
+                    removal,
+                    packages: vec![row],
+                }),
+            }
+        }
+        groups.sort_by(|a, b| a.removal.cmp(&b.removal));
+        for group in &mut groups {
+            group.packages.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        let ids: HashSet<String> = packages.iter().map(|(id, _)| id.clone()).collect();
+        let action = packages
+            .first()
+            .map(|(_, first)| *first)
+            .filter(|first| packages.iter().all(|(_, action)| action == first));
+        let unresolved = self
+            .dependency_graph
+            .unresolved(&ids, &self.installed_packages);
+        PurgeReview {
+            // Packages whose dependents stay installed can break them, so
+            // they need the same confirmation as risky removal levels.
+            needs_typed_confirmation: groups.iter().any(|g| g.removal.is_risky())
+                || !unresolved.is_empty(),
+            groups,
+            unresolved,
+            packages,
+            action,
+            typed: String::new(),
+        }
+    }
+
+    fn draw_purge_review(&mut self, ctx: &egui::Context) {
+        let Some(review) = &mut self.purge_review else {
+            return;
+        };
+        let action = review.action;
+        let total = review.packages.len();
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new("Review Purge")
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(match action {
+                    Some(action) => format!(
+                        "{} {} package(s) for the current user:",
+                        action.label(),
+                        total
+                    ),
+                    None => format!(
+                        "Apply the actions below to {} package(s) for the current user:",
+                        total
+                    ),
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .show(ui, |ui| {
+                        for group in &review.groups {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} ({})",
+                                    group.removal.label(),
+                                    group.packages.len()
+                                ))
+                                .strong()
+                                .color(self.theme.removal_color(&group.removal)),
+                            );
+                            for (id, package_action, needed_by) in &group.packages {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.add_space(12.0);
+                                    ui.label(id);
+                                    if action.is_none() {
+                                        ui.label(
+                                            egui::RichText::new(package_action.label())
+                                                .color(self.theme.on_surface_variant),
+                                        );
+                                    }
+                                    if !needed_by.is_empty() {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "needed by {}",
+                                                needed_by.join(", ")
+                                            ))
+                                            .color(self.theme.status_warn),
+                                        );
+                                    }
+                                    if let Some((_, dependents)) =
+                                        review.unresolved.iter().find(|(u, _)| u == id)
+                                    {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "still needed by installed {}",
+                                                dependents.join(", ")
+                                            ))
+                                            .color(self.theme.status_err),
+                                        );
+                                    }
+                                });
+                            }
+                            ui.add_space(6.0);
+                        }
+                    });
+                ui.separator();
+                if review.needs_typed_confirmation {
+                    let risk = if review.unresolved.is_empty() {
+                        "This selection includes Expert or Unsafe packages, which can break \
+                         your device."
+                    } else {
+                        "This selection removes packages that installed apps still need, \
+                         which can break them."
+                    };
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} Type {} to continue.",
+                            risk, CONFIRMATION_WORD
+                        ))
+                        .color(self.theme.danger),
+                    );
+                    ui.text_edit_singleline(&mut review.typed);
+                    ui.add_space(4.0);
+                }
+                let confirmed =
+                    !review.needs_typed_confirmation || review.typed.trim() == CONFIRMATION_WORD;
+                ui.horizontal(|ui| {
+                    confirm = ui
+                        .add_enabled(
+                            confirmed,
+                            egui::Button::new(format!(
+                                "{} {}",
+                                action.map_or("Apply", |action| action.label()),
+                                total
+                            ))
+                            .fill(self.theme.danger),
+                        )
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if confirm {
+            let review = self.purge_review.take();
+            if let (Some(review), Some(serial)) = (review, self.selected_serial.clone()) {
+                self.start_batch(AdbCommand::Purge(
+                    serial,
+                    self.selected_user,
+                    review.packages,
+                    self.backup_before_purge,
+                ));
+            }
+        } else if cancel {
+            self.purge_review = None;
+        }
+    }
+
     fn is_busy(&self) -> bool {
         self.app_status == AppStatus::Busy
     }
 
+    fn visible_packages(&self) -> Option<&HashSet<String>> {
+        match self.view {
+            PackageView::Installed | PackageView::Unlisted => Some(&self.installed_packages),
+            PackageView::Removed => Some(&self.removed_packages),
+            PackageView::History => None,
+        }
+    }
+
+    /// Whether the current view purges its selection rather than restoring it.
+    fn is_purge_view(&self) -> bool {
+        matches!(self.view, PackageView::Installed | PackageView::Unlisted)
+    }
+
+    /// The selected device and user, the key for per-user caches.
+    fn device_user(&self) -> Option<DeviceUserKey> {
+        self.selected_serial
+            .clone()
+            .map(|serial| (serial, self.selected_user))
+    }
+
+    fn clear_device_packages(&mut self) {
+        self.users.clear();
+        self.installed_for.clear();
+        self.installed_packages.clear();
+        self.removed_packages.clear();
+        self.hidden_packages.clear();
+        self.disabled_packages.clear();
+        self.suspended_packages.clear();
+        self.package_details.clear();
+        self.unlisted_packages.clear();
+    }
+
+    fn rebuild_unlisted(&mut self) {
+        let listed: HashSet<&str> = self.uad_packages.iter().map(|p| p.id.as_str()).collect();
+        let mut unlisted: Vec<PackageInfo> = self
+            .package_details
+            .values()
+            .filter(|d| !listed.contains(d.id.as_str()))
+            .map(|d| {
+                let description = match &d.installer {
+                    Some(installer) => {
+                        format!("{} app, installed by {}", d.kind_label(), installer)
+                    }
+                    None => format!("{} app", d.kind_label()),
+                };
+                PackageInfo::unlisted(&d.id, description)
+            })
+            .collect();
+        unlisted.sort_by(|a, b| a.id.cmp(&b.id));
+        self.unlisted_packages = unlisted;
+    }
+
+    fn reload_history(&mut self) {
+        self.selected_history.clear();
+        match journal::load() {
+            Ok(entries) => self.history = entries,
+            Err(e) => self.status_message = format!("Error: Failed to read journal: {}", e),
+        }
+    }
+
+    /// Whether history entry `i` is still in effect, so undoing it makes
+    /// sense.
+    fn can_undo(&self, i: usize) -> bool {
+        self.history[i].can_revert(&self.history[i + 1..])
+    }
+
+    /// Journal entries for the current device and user, newest session first.
+    fn history_sessions(&self) -> Vec<(u64, Vec<usize>)> {
+        let mut sessions: Vec<(u64, Vec<usize>)> = Vec::new();
+        for (i, entry) in self.history.iter().enumerate() {
+            let other_device = self
+                .selected_serial
+                .as_ref()
+                .is_some_and(|serial| *serial != entry.serial);
+            if other_device || entry.user != self.selected_user {
+                continue;
+            }
+            match sessions.iter_mut().find(|(id, _)| *id == entry.session) {
+                Some((_, indices)) => indices.push(i),
+                None => sessions.push((entry.session, vec![i])),
+            }
+        }
+        sessions.sort_by_key(|(id, _)| std::cmp::Reverse(*id));
+        sessions
+    }
+
+    fn draw_history(&mut self, ui: &mut egui::Ui) {
+        let sessions = self.history_sessions();
+        if sessions.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label(egui::RichText::new("No operations recorded yet.").weak());
+            });
+            return;
+        }
+        ui.set_enabled(!self.is_busy());
+        for (session, indices) in sessions {
+            let undoable: Vec<usize> = indices
+                .iter()
+                .copied()
+                .filter(|&i| self.can_undo(i))
+                .collect();
+            egui::Frame::none()
+                .inner_margin(egui::Margin::same(12.0))
+                .rounding(ui.style().visuals.widgets.noninteractive.rounding)
+                .fill(self.theme.surface)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let started = chrono::DateTime::from_timestamp_millis(session as i64)
+                            .map(|t| {
+                                t.with_timezone(&chrono::Local)
+                                    .format("%Y-%m-%d %H:%M:%S")
+                                    .to_string()
+                            })
+                            .unwrap_or_default();
+                        let serial = &self.history[indices[0]].serial;
+                        ui.label(egui::RichText::new(started).strong());
+                        ui.label(
+                            egui::RichText::new(format!("{} · {} packages", serial, indices.len()))
+                                .color(self.theme.on_surface_variant),
+                        );
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("Select Session").clicked() {
+                                self.selected_history.extend(undoable.iter().copied());
+                            }
+                        });
+                    });
+                    for &i in &indices {
+                        let entry = &self.history[i];
+                        ui.horizontal(|ui| {
+                            let mut checked = self.selected_history.contains(&i);
+                            if ui
+                                .add_enabled(
+                                    undoable.contains(&i),
+                                    egui::Checkbox::new(&mut checked, ""),
+                                )
+                                .changed()
+                            {
+                                if checked {
+                                    self.selected_history.insert(i);
+                                } else {
+                                    self.selected_history.remove(&i);
+                                }
+                            }
+                            ui.label(egui::RichText::new(&entry.package).strong());
+                            ui.label(entry.label());
+                            let color = if entry.outcome.is_success() {
+                                self.theme.status_ok
+                            } else {
+                                self.theme.status_err
+                            };
+                            ui.label(egui::RichText::new(entry.outcome.message()).color(color));
+                        });
+                    }
+                });
+            ui.add_space(6.0);
+        }
+    }
+
+    fn package_state(&self, id: &str) -> adb::PackageState {
+        if self.hidden_packages.contains(id) {
+            adb::PackageState::Hidden
+        } else if self.removed_packages.contains(id) {
+            adb::PackageState::Removed
+        } else if self.disabled_packages.contains(id) {
+            adb::PackageState::Disabled
+        } else if self.suspended_packages.contains(id) {
+            adb::PackageState::Suspended
+        } else {
+            adb::PackageState::Enabled
+        }
+    }
+
+    fn removed_count(&self) -> usize {
+        self.uad_packages
+            .iter()
+            .filter(|info| self.removed_packages.contains(&info.id))
+            .count()
+    }
+
+    fn select_device(&mut self, serial: String) {
+        self.selected_serial = Some(serial);
+        self.selected_user = adb::PRIMARY_USER;
+        self.refresh_selection();
+    }
+
+    fn select_user(&mut self, user: u32) {
+        self.selected_user = user;
+        self.selected_history.clear();
+        self.refresh_selection();
+    }
+
+    /// Reloads the packages of the selected device and user.
+    fn refresh_selection(&mut self) {
+        let Some(serial) = self.selected_serial.clone() else {
+            return;
+        };
+        self.selected.clear();
+        self.active_selection = None;
+        self.app_status = AppStatus::Busy;
+        self.status_message = "Loading packages...".to_string();
+        self.command_tx
+            .send(AdbCommand::Refresh(serial, self.selected_user))
+            .unwrap();
+    }
+
+    /// Picks the user or profile to list and act on. Hidden on devices with
+    /// a single user.
+    fn draw_user_picker(&mut self, ui: &mut egui::Ui) {
+        if self.users.len() < 2 {
+            return;
+        }
+        let selected_text = self
+            .users
+            .iter()
+            .find(|u| u.id == self.selected_user)
+            .map_or_else(|| "Select a user".to_string(), user_label);
+        let mut picked = None;
+        ui.add_enabled_ui(!self.is_busy(), |ui| {
+            egui::ComboBox::from_id_source("user_picker")
+                .width(ui.available_width())
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for user in &self.users {
+                        let is_current = user.id == self.selected_user;
+                        if ui.selectable_label(is_current, user_label(user)).clicked()
+                            && !is_current
+                        {
+                            picked = Some(user.id);
+                        }
+                    }
+                });
+        });
+        if let Some(user) = picked {
+            self.select_user(user);
+        }
+        ui.add_space(10.0);
+    }
+
+    fn draw_device_picker(&mut self, ui: &mut egui::Ui) {
+        if self.devices.len() < 2 {
+            return;
+        }
+        let selected_text = self
+            .devices
+            .iter()
+            .find(|d| Some(&d.serial) == self.selected_serial.as_ref())
+            .map_or_else(|| "Select a device".to_string(), |d| d.display_name());
+        let mut picked = None;
+        ui.add_enabled_ui(!self.is_busy(), |ui| {
+            egui::ComboBox::from_id_source("device_picker")
+                .width(ui.available_width())
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for device in &self.devices {
+                        let is_current = Some(&device.serial) == self.selected_serial.as_ref();
+                        let mut label = if device.is_authorized() {
+                            device.display_name()
+                        } else {
+                            format!("{} [{}]", device.display_name(), device.state)
+                        };
+                        if device.is_wireless() {
+                            label = format!("📶 {}", label);
+                        }
+                        let response = ui.add_enabled(
+                            device.is_authorized(),
+                            egui::SelectableLabel::new(is_current, label),
+                        );
+                        if response.clicked() && !is_current {
+                            picked = Some(device.serial.clone());
+                        }
+                    }
+                });
+        });
+        if let Some(serial) = picked {
+            self.select_device(serial);
+        }
+        ui.add_space(10.0);
+    }
+
+    /// Explains what to do about devices adb sees but can't use, or about
+    /// seeing no device at all.
+    fn draw_device_guidance(&self, ui: &mut egui::Ui) {
+        let problems: Vec<&adb::Device> =
+            self.devices.iter().filter(|d| !d.is_authorized()).collect();
+        let nothing_found = self.devices_scanned && self.devices.is_empty();
+        if problems.is_empty() && !nothing_found {
+            return;
+        }
+        ui.add_space(10.0);
+        egui::Frame::none()
+            .inner_margin(egui::Margin::same(8.0))
+            .rounding(ui.style().visuals.widgets.noninteractive.rounding)
+            .fill(self.theme.surface)
+            .show(ui, |ui| {
+                if nothing_found {
+                    ui.label(
+                        egui::RichText::new("⚠ No device found")
+                            .strong()
+                            .color(self.theme.status_warn),
+                    );
+                    ui.label(adb::NO_DEVICE_GUIDANCE);
+                }
+                for device in problems {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "⚠ {} is {}",
+                            device.display_name(),
+                            device.state
+                        ))
+                        .strong()
+                        .color(self.theme.status_warn),
+                    );
+                    if let Some(guidance) = device.state.guidance() {
+                        ui.label(guidance);
+                    }
+                }
+            });
+    }
+
+    /// The adb server's version, with a restart button. A server another
+    /// tool started is kept until the user restarts it here.
+    fn draw_server_status(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(6.0);
+        let (text, color) = match &self.server {
+            None => (
+                "ADB server: unknown".to_string(),
+                self.theme.on_surface_variant,
+            ),
+            Some(status) => match (status.server_version, status.adb_version) {
+                (None, _) => (
+                    "ADB server: not running".to_string(),
+                    self.theme.on_surface_variant,
+                ),
+                (Some(server), Some(adb)) if status.is_mismatched() => (
+                    format!(
+                        "⚠ ADB server version {} doesn't match adb version {}",
+                        server, adb
+                    ),
+                    self.theme.status_warn,
+                ),
+                (Some(server), _) => (
+                    format!("ADB server version {}", server),
+                    self.theme.on_surface_variant,
+                ),
+            },
+        };
+        ui.label(egui::RichText::new(text).color(color));
+        if ui
+            .add_enabled(
+                !self.is_busy(),
+                egui::Button::new("♻ Restart ADB Server")
+                    .min_size([ui.available_width(), 0.0].into()),
+            )
+            .on_hover_text(
+                "Kill the running adb server, even one another tool started, and start a new one.",
+            )
+            .clicked()
+        {
+            self.app_status = AppStatus::Busy;
+            self.status_message = "Restarting ADB server...".to_string();
+            self.command_tx.send(AdbCommand::RestartServer).unwrap();
+        }
+    }
+
     fn draw_custom_title_bar(&mut self, ctx: &egui::Context) {
         let title_bar_height = 30.0;
 
@@ -291,6 +1847,8 @@ impl DebloaterApp {
 
                 ui.add_space(25.0);
                 egui::Frame::group(ui.style()).show(ui, |ui| {
+                    self.draw_device_picker(ui);
+                    self.draw_user_picker(ui);
                     ui.vertical_centered(|ui| {
                         ui.label(egui::RichText::new(&self.device_name).size(22.0).strong());
                         let subtext = if self.device_name == "No Device Connected" {
@@ -300,6 +1858,7 @@ impl DebloaterApp {
                         };
                         ui.label(egui::RichText::new(subtext).color(self.theme.on_surface_variant));
                     });
+                    self.draw_device_guidance(ui);
                     ui.add_space(10.0);
                     if ui
                         .add_sized(
@@ -310,40 +1869,155 @@ impl DebloaterApp {
                     {
                         self.app_status = AppStatus::Busy;
                         self.status_message = "Scanning for devices...".to_string();
-                        self.command_tx.send(AdbCommand::Refresh).unwrap();
+                        self.command_tx.send(AdbCommand::ScanDevices).unwrap();
+                        let _ = self.command_tx.send(AdbCommand::CheckServer);
+                    }
+                    if ui
+                        .add_sized(
+                            [ui.available_width(), 30.0],
+                            egui::Button::new("📶 Wireless Debugging"),
+                        )
+                        .clicked()
+                    {
+                        self.reload_endpoints();
+                        self.wireless = Some(WirelessForm::default());
                     }
+                    self.draw_server_status(ui);
                 });
                 ui.add_space(10.0);
                 egui::Frame::group(ui.style()).show(ui, |ui| {
-                    let text = format!("🔥 Purge ({})", self.selected.len());
+                    let text = match self.view {
+                        PackageView::Installed | PackageView::Unlisted => {
+                            format!("🔥 Purge ({})", self.selected.len())
+                        }
+                        PackageView::Removed => format!("♻ Restore ({})", self.selected.len()),
+                        PackageView::History => {
+                            format!("↩ Undo ({})", self.selected_history.len())
+                        }
+                    };
                     let button = egui::Button::new(egui::RichText::new(text).size(20.0).strong());
-                    ui.style_mut().visuals.widgets.active.bg_fill = self.theme.danger;
-                    ui.add_enabled_ui(!self.selected.is_empty() && !self.is_busy(), |ui| {
-                        if ui.add_sized([ui.available_width(), 50.0], button).clicked() {
-                            self.app_status = AppStatus::Busy;
-                            let packages: Vec<String> = self.selected.iter().cloned().collect();
-                            self.command_tx
-                                .send(AdbCommand::Uninstall(packages))
-                                .unwrap();
+                    if self.is_purge_view() {
+                        ui.style_mut().visuals.widgets.active.bg_fill = self.theme.danger;
+                        ui.horizontal(|ui| {
+                            ui.label("Action");
+                            egui::ComboBox::from_id_source("purge_action")
+                                .width(ui.available_width())
+                                .selected_text(self.purge_action.label())
+                                .show_ui(ui, |ui| {
+                                    for action in adb::PurgeAction::ALL {
+                                        ui.selectable_value(
+                                            &mut self.purge_action,
+                                            action,
+                                            action.label(),
+                                        );
+                                    }
+                                });
+                        });
+                        if self.purge_action == adb::PurgeAction::Uninstall {
+                            ui.checkbox(&mut self.backup_before_purge, "Back up APKs first")
+                                .on_hover_text(
+                                    "Pulls each package's APKs to the backup folder and skips \
+                                     packages whose backup fails.",
+                                );
                         }
-                    });
+                    } else {
+                        ui.checkbox(&mut self.restore_from_backup, "Reinstall from backups")
+                            .on_hover_text(
+                                "Reinstalls uninstalled packages from their APK backup when \
+                                 one exists, keeping updates the system image lacks.",
+                            );
+                    }
+                    let has_selection = match self.view {
+                        PackageView::History => !self.selected_history.is_empty(),
+                        _ => !self.selected.is_empty(),
+                    };
+                    let can_run =
+                        has_selection && !self.is_busy() && self.selected_serial.is_some();
+                    if self.batch_running {
+                        ui.add(egui::ProgressBar::new(self.progress).show_percentage());
+                        let cancelling = self.cancel_requested.load(Ordering::Relaxed);
+                        let cancel =
+                            egui::Button::new(egui::RichText::new("✖ Cancel").size(20.0).strong());
+                        ui.add_enabled_ui(!cancelling, |ui| {
+                            if ui
+                                .add_sized([ui.available_width(), 50.0], cancel)
+                                .on_hover_text("Stops after the package being processed.")
+                                .clicked()
+                            {
+                                self.cancel_requested.store(true, Ordering::Relaxed);
+                                self.status_message =
+                                    "Cancelling after the current package...".to_string();
+                            }
+                        });
+                    } else {
+                        ui.add_enabled_ui(can_run, |ui| {
+                            if ui.add_sized([ui.available_width(), 50.0], button).clicked() {
+                                if self.is_purge_view() {
+                                    let packages = self
+                                        .selected
+                                        .iter()
+                                        .map(|id| (id.clone(), self.purge_action))
+                                        .collect();
+                                    self.purge_review = Some(self.review_purge(packages));
+                                } else {
+                                    self.run_restore();
+                                }
+                            }
+                        });
+                    }
                 });
-
-                ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                    ui.add_space(10.0);
-                    egui::Frame::group(ui.style()).show(ui, |ui| {
-                        if ui
+                ui.add_space(10.0);
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.columns(2, |columns| {
+                        // Only the purge views select packages a profile can
+                        // purge again.
+                        let can_save = self.is_purge_view() && !self.selected.is_empty();
+                        columns[0].add_enabled_ui(can_save, |ui| {
+                            if ui
+                                .add_sized(
+                                    [ui.available_width(), 35.0],
+                                    egui::Button::new("💾 Save Profile"),
+                                )
+                                .clicked()
+                            {
+                                self.save_profile();
+                            }
+                        });
+                        if columns[1]
                             .add_sized(
-                                [ui.available_width(), 35.0],
-                                egui::Button::new("Reboot Device"),
+                                [columns[1].available_width(), 35.0],
+                                egui::Button::new("📁 Load Profile"),
                             )
                             .clicked()
                         {
-                            self.app_status = AppStatus::Busy;
-                            self.status_message = "Sending reboot command...".to_string();
-                            self.command_tx.send(AdbCommand::Reboot).unwrap();
+                            self.load_profile();
                         }
                     });
+                });
+
+                ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                    ui.add_space(10.0);
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.add_enabled_ui(
+                            self.selected_serial.is_some() && !self.is_busy(),
+                            |ui| {
+                                if ui
+                                    .add_sized(
+                                        [ui.available_width(), 35.0],
+                                        egui::Button::new("Reboot Device"),
+                                    )
+                                    .clicked()
+                                {
+                                    if let Some(serial) = self.selected_serial.clone() {
+                                        self.app_status = AppStatus::Busy;
+                                        self.status_message =
+                                            "Sending reboot command...".to_string();
+                                        self.command_tx.send(AdbCommand::Reboot(serial)).unwrap();
+                                    }
+                                }
+                            },
+                        );
+                    });
                     ui.add_space(10.0);
                     ui.separator();
                     ui.add_space(5.0);
@@ -385,7 +2059,11 @@ impl DebloaterApp {
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
                 .show(ui, |ui| {
-                    self.draw_package_list(ui, filtered);
+                    if self.view == PackageView::History {
+                        self.draw_history(ui);
+                    } else {
+                        self.draw_package_list(ui, filtered);
+                    }
                 });
         });
     }
@@ -393,6 +2071,23 @@ impl DebloaterApp {
     fn draw_top_bar(&mut self, ui: &mut egui::Ui, filtered: &[PackageInfo]) {
         ui.horizontal(|ui| {
             ui.add_space(5.0);
+            let previous_view = self.view;
+            let removed_label = format!("Removed ({})", self.removed_count());
+            let unlisted_label = format!("Unlisted ({})", self.unlisted_packages.len());
+            ui.selectable_value(&mut self.view, PackageView::Installed, "Installed");
+            ui.selectable_value(&mut self.view, PackageView::Removed, removed_label);
+            ui.selectable_value(&mut self.view, PackageView::Unlisted, unlisted_label);
+            ui.selectable_value(&mut self.view, PackageView::History, "History");
+            if self.view != previous_view {
+                self.selected.clear();
+                self.active_selection = None;
+                if self.view == PackageView::History {
+                    self.reload_history();
+                }
+            }
+            if self.view == PackageView::History {
+                return;
+            }
             egui::Frame::group(ui.style()).show(ui, |ui| {
                 ui.add(
                     egui::TextEdit::singleline(&mut self.search_query)
@@ -401,21 +2096,37 @@ impl DebloaterApp {
                         .frame(false),
                 );
             });
-            egui::ComboBox::from_id_source("list_filter")
-                .selected_text(&self.filter_list)
-                .show_ui(ui, |ui| {
-                    for name in &self.all_lists {
-                        ui.selectable_value(&mut self.filter_list, name.clone(), name);
-                    }
-                });
-            egui::ComboBox::from_id_source("removal_filter")
-                .selected_text(&self.filter_removal)
-                .show_ui(ui, |ui| {
-                    for name in &self.all_removals {
-                        ui.selectable_value(&mut self.filter_removal, name.clone(), name);
-                    }
-                });
+            // Unlisted packages have no list or removal level to filter by.
+            if self.view != PackageView::Unlisted {
+                egui::ComboBox::from_id_source("list_filter")
+                    .selected_text(self.filter_list.as_ref().map_or("All", PackageList::label))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.filter_list, None, "All");
+                        for list in &self.all_lists {
+                            ui.selectable_value(
+                                &mut self.filter_list,
+                                Some(list.clone()),
+                                list.label(),
+                            );
+                        }
+                    });
+                egui::ComboBox::from_id_source("removal_filter")
+                    .selected_text(self.filter_removal.as_ref().map_or("All", Removal::label))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.filter_removal, None, "All");
+                        for removal in &self.all_removals {
+                            ui.selectable_value(
+                                &mut self.filter_removal,
+                                Some(removal.clone()),
+                                removal.label(),
+                            );
+                        }
+                    });
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("📂 Load List").clicked() {
+                    self.import_list();
+                }
                 if ui.button("Deselect All").clicked() {
                     self.selected.clear();
                 }
@@ -426,6 +2137,12 @@ impl DebloaterApp {
                 }
             });
         });
+        if matches!(self.view, PackageView::Installed | PackageView::Removed) {
+            ui.horizontal(|ui| {
+                ui.add_space(5.0);
+                widgets::removal_legend(ui, &self.theme);
+            });
+        }
     }
 
     fn draw_package_list(&mut self, ui: &mut egui::Ui, filtered: Vec<PackageInfo>) {
@@ -444,11 +2161,27 @@ impl DebloaterApp {
                 let is_active = self
                     .active_selection
                     .as_ref()
-                    .map_or(false, |s| s.id == info.id);
+                    .is_some_and(|s| s.id == info.id);
+                let state = self.package_state(&info.id);
+                let installed_for = self.installed_for.get(&info.id);
+                let users: Vec<(&adb::DeviceUser, bool)> = self
+                    .users
+                    .iter()
+                    .filter(|_| self.users.len() > 1)
+                    .map(|u| (u, installed_for.is_some_and(|ids| ids.contains(&u.id))))
+                    .collect();
 
                 let response = ui
                     .scope(|ui| {
-                        widgets::package_card(ui, &self.theme, &info, is_selected, is_active);
+                        widgets::package_card(
+                            ui,
+                            &self.theme,
+                            info,
+                            state,
+                            &users,
+                            is_selected,
+                            is_active,
+                        );
                     })
                     .response
                     .interact(egui::Sense::click());
@@ -458,6 +2191,9 @@ impl DebloaterApp {
                         self.selected.remove(&info.id);
                     } else {
                         self.selected.insert(info.id.clone());
+                        if self.view == PackageView::Installed {
+                            self.check_dependents(&info.id);
+                        }
                     }
                     self.active_selection = Some(info.clone());
                 }
@@ -467,6 +2203,9 @@ impl DebloaterApp {
     }
 
     fn draw_detail_panel(&mut self, ui: &mut egui::Ui) {
+        if let Some(id) = self.active_selection.as_ref().map(|info| info.id.clone()) {
+            self.request_metadata(&id);
+        }
         if let Some(info) = &self.active_selection {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.label(egui::RichText::new(&info.id).heading().strong());
@@ -477,6 +2216,14 @@ impl DebloaterApp {
                     ui.label(d.replace("\\n", "\n"));
                     ui.add_space(10.0);
                 }
+                if let Some(key) = self.device_user() {
+                    let metadata = self
+                        .package_metadata
+                        .get(&key)
+                        .and_then(|m| m.get(&info.id));
+                    widgets::package_metadata(ui, &self.theme, metadata);
+                    ui.add_space(10.0);
+                }
                 if let Some(l) = &info.labels {
                     if !l.is_empty() {
                         ui.label(egui::RichText::new("Labels").strong());
@@ -502,6 +2249,35 @@ impl DebloaterApp {
                         ui.add_space(10.0);
                     }
                 }
+                if let Some(details) = self.package_details.get(&info.id) {
+                    ui.label(egui::RichText::new("On Device").strong());
+                    egui::Grid::new("device_details")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Type");
+                            ui.label(details.kind_label());
+                            ui.end_row();
+                            ui.label("Installer");
+                            ui.label(details.installer.as_deref().unwrap_or("Unknown"));
+                            ui.end_row();
+                            ui.label("APK");
+                            ui.label(&details.apk_path);
+                            ui.end_row();
+                        });
+                    ui.add_space(10.0);
+                }
+                ui.label(egui::RichText::new("Source").strong());
+                ui.label(info.source.to_string());
+                ui.add_space(10.0);
+                for (key, value) in &info.extra {
+                    let text = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    ui.label(egui::RichText::new(key).strong());
+                    ui.label(text);
+                    ui.add_space(10.0);
+                }
             });
         } else {
             ui.centered_and_justified(|ui| {
@@ -511,23 +2287,59 @@ impl DebloaterApp {
     }
 }
 
+/// The packages of a batch from `processed` on, which a cancel skipped.
+fn unprocessed(packages: &[(String, adb::PurgeAction)], processed: usize) -> Vec<String> {
+    packages[processed..]
+        .iter()
+        .map(|(pkg, _)| pkg.clone())
+        .collect()
+}
+
+fn user_label(user: &adb::DeviceUser) -> String {
+    let icon = if user.is_work_profile() {
+        "💼"
+    } else {
+        "👤"
+    };
+    format!("{} {}", icon, user.display_name())
+}
+
+/// The distinct values of `field` across `packages`, in sort order.
+fn filter_values<T: Ord>(
+    packages: &[PackageInfo],
+    field: impl Fn(&PackageInfo) -> Option<T>,
+) -> Vec<T> {
+    packages
+        .iter()
+        .filter_map(field)
+        .collect::<BTreeSet<T>>()
+        .into_iter()
+        .collect()
+}
+
 impl eframe::App for DebloaterApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.handle_adb_results();
-        let filtered: Vec<PackageInfo> = self
-            .uad_packages
+        let (packages, filter) = if self.view == PackageView::Unlisted {
+            let filter = config::PackageFilter {
+                search: &self.search_query,
+                ..Default::default()
+            };
+            (&self.unlisted_packages, filter)
+        } else {
+            let filter = config::PackageFilter {
+                search: &self.search_query,
+                list: self.filter_list.as_ref(),
+                removal: self.filter_removal.as_ref(),
+            };
+            (&self.uad_packages, filter)
+        };
+        let filtered: Vec<PackageInfo> = packages
             .iter()
             .filter(|info| {
-                self.installed_packages.contains(&info.id)
-                    && (self.search_query.is_empty()
-                        || info
-                            .id
-                            .to_lowercase()
-                            .contains(&self.search_query.to_lowercase()))
-                    && (self.filter_list == "All"
-                        || info.list.as_deref() == Some(&self.filter_list))
-                    && (self.filter_removal == "All"
-                        || info.removal.as_deref() == Some(&self.filter_removal))
+                self.visible_packages()
+                    .is_some_and(|visible| visible.contains(&info.id))
+                    && filter.matches(info)
             })
             .cloned()
             .collect();
@@ -535,6 +2347,11 @@ impl eframe::App for DebloaterApp {
         self.draw_custom_title_bar(ctx);
         self.draw_side_panel(ctx);
         self.draw_central_panel(ctx, filtered);
+        self.draw_report_window(ctx);
+        self.draw_profile_window(ctx);
+        self.draw_dependency_prompt(ctx);
+        self.draw_purge_review(ctx);
+        self.draw_wireless_window(ctx);
         ctx.request_repaint();
     }
 }
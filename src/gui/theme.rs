@@ -1,4 +1,5 @@
 // src/gui/theme.rs
+use crate::config::Removal;
 use eframe::egui;
 use egui::{style::Spacing, FontData, FontDefinitions, FontFamily, Style, TextStyle, Visuals};
 
@@ -17,6 +18,13 @@ pub struct Theme {
     pub status_warn: egui::Color32,
     pub status_err: egui::Color32,
     pub status_neutral: egui::Color32,
+
+    pub removal_safe: egui::Color32,
+    pub removal_advanced: egui::Color32,
+    pub removal_expert: egui::Color32,
+    pub removal_unsafe: egui::Color32,
+    pub removal_system: egui::Color32,
+    pub removal_unknown: egui::Color32,
 }
 
 impl Default for Theme {
@@ -36,6 +44,26 @@ impl Default for Theme {
             status_warn: egui::Color32::from_rgb(255, 180, 0),
             status_err: egui::Color32::from_rgb(255, 80, 80),
             status_neutral: egui::Color32::from_rgb(150, 150, 150),
+
+            removal_safe: egui::Color32::from_rgb(30, 200, 150),
+            removal_advanced: egui::Color32::from_rgb(255, 180, 0),
+            removal_expert: egui::Color32::from_rgb(255, 120, 40),
+            removal_unsafe: egui::Color32::from_rgb(255, 50, 50),
+            removal_system: egui::Color32::from_rgb(160, 110, 255),
+            removal_unknown: egui::Color32::from_rgb(150, 150, 150),
+        }
+    }
+}
+
+impl Theme {
+    pub fn removal_color(&self, removal: &Removal) -> egui::Color32 {
+        match removal {
+            Removal::Safe => self.removal_safe,
+            Removal::Advanced => self.removal_advanced,
+            Removal::Expert => self.removal_expert,
+            Removal::Unsafe => self.removal_unsafe,
+            Removal::System => self.removal_system,
+            Removal::Other(_) | Removal::Unknown => self.removal_unknown,
         }
     }
 }
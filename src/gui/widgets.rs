@@ -1,5 +1,6 @@
 // src/gui/widgets.rs
-use crate::config::PackageInfo;
+use crate::adb::{DeviceUser, PackageMetadata, PackageState};
+use crate::config::{PackageInfo, Removal};
 use crate::gui::theme::Theme;
 use eframe::egui;
 
@@ -7,6 +8,8 @@ pub fn package_card(
     ui: &mut egui::Ui,
     theme: &Theme,
     info: &PackageInfo,
+    state: PackageState,
+    users: &[(&DeviceUser, bool)],
     is_selected: bool,
     is_active: bool,
 ) {
@@ -43,22 +46,28 @@ pub fn package_card(
                     );
                 }
 
-                let status_color = match info.removal.as_deref() {
-                    Some("Recommended") => theme.status_ok,
-                    Some("Advanced") => theme.status_warn,
-                    Some("Expert") => theme.status_err,
-                    _ => theme.status_neutral,
-                };
-                let (dot_rect, _) =
-                    ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
-                ui.painter()
-                    .circle_filled(dot_rect.center(), 4.0, status_color);
+                removal_dot(ui, theme, info.removal_level());
 
                 ui.add_space(4.0);
 
                 // This vertical layout will now expand to fill the rest of the horizontal space
                 ui.vertical(|ui| {
-                    ui.label(egui::RichText::new(&info.id).size(17.0).strong());
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(&info.id).size(17.0).strong());
+                        let state_color = match state {
+                            PackageState::Enabled => theme.status_ok,
+                            PackageState::Disabled | PackageState::Suspended => theme.status_warn,
+                            PackageState::Hidden | PackageState::Removed => theme.status_neutral,
+                        };
+                        ui.label(
+                            egui::RichText::new(state.label())
+                                .size(13.0)
+                                .color(state_color),
+                        );
+                        for (user, installed) in users {
+                            user_chip(ui, theme, user, *installed);
+                        }
+                    });
                     if let Some(desc) = &info.description {
                         ui.add_space(2.0);
                         let truncated = desc.lines().next().unwrap_or("").to_string();
@@ -72,3 +81,115 @@ pub fn package_card(
             });
         });
 }
+
+/// Whether a package is installed for one user of a multi-user device.
+fn user_chip(ui: &mut egui::Ui, theme: &Theme, user: &DeviceUser, installed: bool) {
+    let icon = if user.is_work_profile() {
+        "💼"
+    } else {
+        "👤"
+    };
+    let (color, hover) = if installed {
+        (theme.status_ok, "Installed for")
+    } else {
+        (theme.status_neutral, "Not installed for")
+    };
+    ui.label(
+        egui::RichText::new(format!("{}{}", icon, user.id))
+            .size(13.0)
+            .color(color),
+    )
+    .on_hover_text(format!("{} {}", hover, user.display_name()));
+}
+
+fn removal_dot(ui: &mut egui::Ui, theme: &Theme, removal: &Removal) {
+    let (dot_rect, response) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+    ui.painter()
+        .circle_filled(dot_rect.center(), 4.0, theme.removal_color(removal));
+    response.on_hover_text(removal.label());
+}
+
+/// One dot and label per removal level, explaining the card dots.
+pub fn removal_legend(ui: &mut egui::Ui, theme: &Theme) {
+    ui.horizontal(|ui| {
+        for removal in &Removal::ALL {
+            removal_dot(ui, theme, removal);
+            ui.label(
+                egui::RichText::new(removal.label())
+                    .size(12.0)
+                    .color(theme.on_surface_variant),
+            );
+            ui.add_space(6.0);
+        }
+    });
+}
+
+/// The live `dumpsys` details for the detail panel; `None` while loading.
+pub fn package_metadata(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    metadata: Option<&Result<PackageMetadata, String>>,
+) {
+    ui.label(egui::RichText::new("Package Info").strong());
+    let metadata = match metadata {
+        Some(Ok(metadata)) => metadata,
+        Some(Err(e)) => {
+            ui.label(egui::RichText::new(e).color(theme.on_surface_variant));
+            return;
+        }
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(egui::RichText::new("Reading from device...").weak());
+            });
+            return;
+        }
+    };
+    let unknown = || "Unknown".to_string();
+    let version = match (&metadata.version_name, &metadata.version_code) {
+        (Some(name), Some(code)) => format!("{} ({})", name, code),
+        (Some(name), None) => name.clone(),
+        (None, Some(code)) => code.clone(),
+        (None, None) => unknown(),
+    };
+    let kind = match (metadata.system, metadata.updated_system_app) {
+        (true, true) => "System app, updated",
+        (true, false) => "System app",
+        (false, _) => "User app",
+    };
+    egui::Grid::new("package_metadata")
+        .num_columns(2)
+        .show(ui, |ui| {
+            let mut row = |label: &str, value: String| {
+                ui.label(label);
+                ui.label(value);
+                ui.end_row();
+            };
+            row("Version", version);
+            row("Type", kind.to_string());
+            row(
+                "State",
+                metadata.enabled_state.clone().unwrap_or_else(unknown),
+            );
+            row(
+                "Installed",
+                metadata.first_install_time.clone().unwrap_or_else(unknown),
+            );
+            row(
+                "Updated",
+                metadata.last_update_time.clone().unwrap_or_else(unknown),
+            );
+            row("Path", metadata.code_path.clone().unwrap_or_else(unknown));
+        });
+    if !metadata.granted_permissions.is_empty() {
+        egui::CollapsingHeader::new(format!(
+            "Granted permissions ({})",
+            metadata.granted_permissions.len()
+        ))
+        .show(ui, |ui| {
+            for permission in &metadata.granted_permissions {
+                ui.label(egui::RichText::new(permission).size(13.0));
+            }
+        });
+    }
+}
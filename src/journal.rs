@@ -0,0 +1,229 @@
+// src/journal.rs
+use crate::adb::{PmOutcome, PurgeAction};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One package operation performed by P.U.R.G.E., as stored in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Groups the entries of one batch; the batch start time in milliseconds.
+    pub session: u64,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub serial: String,
+    /// The user or profile the operation targeted. Entries written before
+    /// users were tracked all targeted the primary user.
+    #[serde(default)]
+    pub user: u32,
+    pub package: String,
+    pub action: PurgeAction,
+    /// `true` when this entry undid `action` rather than applying it.
+    #[serde(default)]
+    pub revert: bool,
+    pub outcome: PmOutcome,
+    pub app_version: String,
+}
+
+impl JournalEntry {
+    pub fn label(&self) -> &'static str {
+        if self.revert {
+            self.action.revert_label()
+        } else {
+            self.action.label()
+        }
+    }
+
+    /// Whether replaying the inverse of this entry makes sense: it applied
+    /// its action, and no entry in `later` has successfully undone it since.
+    pub fn can_revert(&self, later: &[JournalEntry]) -> bool {
+        !self.revert && self.outcome.is_success() && !later.iter().any(|entry| entry.undoes(self))
+    }
+
+    fn undoes(&self, applied: &JournalEntry) -> bool {
+        self.revert
+            && self.outcome.is_success()
+            && self.serial == applied.serial
+            && self.user == applied.user
+            && self.package == applied.package
+            && self.action == applied.action
+    }
+}
+
+/// The journal is a JSON Lines file that is only ever appended to.
+pub fn journal_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("purge").join("journal.jsonl"))
+}
+
+pub fn new_session() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+pub fn append(entry: &JournalEntry) -> io::Result<()> {
+    let path = journal_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory available"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let line = serde_json::to_string(entry)?;
+    writeln!(file, "{}", line)
+}
+
+/// Builds and appends an entry. Journal failures never abort an operation;
+/// they are reported on stderr instead.
+pub fn record(
+    session: u64,
+    serial: &str,
+    user: u32,
+    package: &str,
+    action: PurgeAction,
+    revert: bool,
+    outcome: &PmOutcome,
+) {
+    let entry = JournalEntry {
+        session,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        serial: serial.to_string(),
+        user,
+        package: package.to_string(),
+        action,
+        revert,
+        outcome: outcome.clone(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    if let Err(e) = append(&entry) {
+        eprintln!("warning: failed to write journal entry: {}", e);
+    }
+}
+
+/// Reads every journal entry, oldest first. Lines that fail to parse are
+/// skipped so one bad write can't hide the rest of the history.
+pub fn load() -> io::Result<Vec<JournalEntry>> {
+    match journal_path() {
+        Some(path) => read(&path),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn read(path: &Path) -> io::Result<Vec<JournalEntry>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(package: &str, action: PurgeAction, revert: bool, outcome: PmOutcome) -> JournalEntry {
+        JournalEntry {
+            session: 1,
+            timestamp: 1,
+            serial: "ABC123".to_string(),
+            user: 0,
+            package: package.to_string(),
+            action,
+            revert,
+            outcome,
+            app_version: "1.0.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn read_skips_a_truncated_last_line() {
+        let path = std::env::temp_dir().join(format!("purge-journal-{}.jsonl", std::process::id()));
+        let first = entry("com.foo", PurgeAction::Uninstall, false, PmOutcome::Success);
+        let second = entry("com.bar", PurgeAction::Hide, true, PmOutcome::NotInstalled);
+        let mut text = String::new();
+        for e in [&first, &second] {
+            text.push_str(&serde_json::to_string(e).unwrap());
+            text.push('\n');
+        }
+        text.push_str(r#"{"session":2,"timestamp":2,"serial":"ABC"#);
+        fs::write(&path, text).unwrap();
+
+        let entries = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].package, "com.foo");
+        assert_eq!(entries[1].action, PurgeAction::Hide);
+        assert!(entries[1].revert);
+        assert_eq!(entries[1].outcome, PmOutcome::NotInstalled);
+    }
+
+    #[test]
+    fn read_treats_a_missing_journal_as_empty() {
+        let path = std::env::temp_dir().join("purge-journal-that-does-not-exist.jsonl");
+        assert!(read(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_defaults_fields_added_later() {
+        let path =
+            std::env::temp_dir().join(format!("purge-journal-old-{}.jsonl", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"session":1,"timestamp":1,"serial":"ABC123","package":"com.foo","action":"disable","outcome":{"status":"success"},"app_version":"1.0.0"}"#,
+        )
+        .unwrap();
+        let entries = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(entries[0].user, 0);
+        assert!(!entries[0].revert);
+    }
+
+    #[test]
+    fn only_successful_applications_can_be_reverted() {
+        let purged = entry("com.foo", PurgeAction::Uninstall, false, PmOutcome::Success);
+        assert!(purged.can_revert(&[]));
+        let failed = entry(
+            "com.foo",
+            PurgeAction::Uninstall,
+            false,
+            PmOutcome::Failure("DELETE_FAILED_INTERNAL_ERROR".to_string()),
+        );
+        assert!(!failed.can_revert(&[]));
+        let restored = entry("com.foo", PurgeAction::Uninstall, true, PmOutcome::Success);
+        assert!(!restored.can_revert(&[]));
+    }
+
+    #[test]
+    fn a_later_successful_revert_settles_the_entry() {
+        let purged = entry("com.foo", PurgeAction::Disable, false, PmOutcome::Success);
+        let enabled = entry("com.foo", PurgeAction::Disable, true, PmOutcome::Success);
+        assert!(!purged.can_revert(std::slice::from_ref(&enabled)));
+
+        let failed = entry(
+            "com.foo",
+            PurgeAction::Disable,
+            true,
+            PmOutcome::NotInstalled,
+        );
+        assert!(purged.can_revert(&[failed]));
+
+        let mut other_user = enabled.clone();
+        other_user.user = 10;
+        let other_package = entry("com.bar", PurgeAction::Disable, true, PmOutcome::Success);
+        let other_action = entry("com.foo", PurgeAction::Hide, true, PmOutcome::Success);
+        assert!(purged.can_revert(&[other_user, other_package, other_action]));
+
+        // Disabling again after the revert starts a new entry to undo.
+        let history = [purged.clone(), enabled, purged];
+        assert!(!history[0].can_revert(&history[1..]));
+        assert!(history[2].can_revert(&history[3..]));
+    }
+}
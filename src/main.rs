@@ -2,8 +2,14 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod adb;
+mod backup;
+mod cli;
 mod config;
+mod deps;
 mod gui;
+mod journal;
+mod profile;
+mod wireless;
 // The 'mod utils;' line has been removed.
 
 use eframe::egui::IconData;
@@ -26,7 +32,33 @@ fn load_icon() -> IconData {
     }
 }
 
+/// Release builds use the GUI subsystem, which starts without a console, so
+/// the CLI would print nothing. Borrowing the console of the shell that
+/// launched us makes its output show up there. The console is attached even
+/// when output is redirected; redirection keeps working because Windows only
+/// points standard handles at the console when they aren't already valid.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // SAFETY: AttachConsole takes no pointers and only fails when there is
+    // nothing to attach to, which leaves the process as it was.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn main() -> Result<(), eframe::Error> {
+    // Any argument (including --help) switches to the headless CLI.
+    if std::env::args_os().len() > 1 {
+        #[cfg(windows)]
+        attach_parent_console();
+        std::process::exit(cli::run());
+    }
+
     let options = NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
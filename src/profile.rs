@@ -0,0 +1,215 @@
+// src/profile.rs
+use crate::adb::PurgeAction;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A named, shareable debloat selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub packages: Vec<ProfileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub id: String,
+    #[serde(default = "default_action")]
+    pub action: PurgeAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+fn default_action() -> PurgeAction {
+    PurgeAction::Uninstall
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(PathBuf, std::io::Error),
+    Json(serde_json::Error),
+    TomlRead(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(path, e) => write!(f, "Failed to access {}: {}", path.display(), e),
+            ProfileError::Json(e) => write!(f, "Invalid profile JSON: {}", e),
+            ProfileError::TomlRead(e) => write!(f, "Invalid profile TOML: {}", e),
+            ProfileError::TomlWrite(e) => write!(f, "Failed to write profile TOML: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+}
+
+/// Loads a profile, picking TOML or JSON from the file extension.
+pub fn load(path: &Path) -> Result<Profile, ProfileError> {
+    let text = fs::read_to_string(path).map_err(|e| ProfileError::Io(path.to_path_buf(), e))?;
+    if is_toml(path) {
+        toml::from_str(&text).map_err(ProfileError::TomlRead)
+    } else {
+        serde_json::from_str(&text).map_err(ProfileError::Json)
+    }
+}
+
+/// Saves a profile, picking TOML or JSON from the file extension.
+pub fn save(profile: &Profile, path: &Path) -> Result<(), ProfileError> {
+    let text = if is_toml(path) {
+        toml::to_string_pretty(profile).map_err(ProfileError::TomlWrite)?
+    } else {
+        serde_json::to_string_pretty(profile).map_err(ProfileError::Json)?
+    };
+    fs::write(path, text).map_err(|e| ProfileError::Io(path.to_path_buf(), e))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryStatus {
+    /// Installed on the device, so the profile action still applies.
+    Installed,
+    /// Already removed for the current user.
+    Removed,
+    /// Known to the debloat lists but not present on this device.
+    Missing,
+    /// Not in any loaded debloat list.
+    Unknown,
+}
+
+impl EntryStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EntryStatus::Installed => "Installed",
+            EntryStatus::Removed => "Already removed",
+            EntryStatus::Missing => "Not on device",
+            EntryStatus::Unknown => "Unknown package",
+        }
+    }
+}
+
+/// Compares a profile against the loaded lists and the current device.
+pub fn diff(
+    profile: &Profile,
+    known: &HashSet<&str>,
+    installed: &HashSet<String>,
+    removed: &HashSet<String>,
+) -> Vec<(ProfileEntry, EntryStatus)> {
+    profile
+        .packages
+        .iter()
+        .map(|entry| {
+            let status = if !known.contains(entry.id.as_str()) {
+                EntryStatus::Unknown
+            } else if installed.contains(&entry.id) {
+                EntryStatus::Installed
+            } else if removed.contains(&entry.id) {
+                EntryStatus::Removed
+            } else {
+                EntryStatus::Missing
+            };
+            (entry.clone(), status)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Profile {
+        Profile {
+            name: "Work phone".to_string(),
+            packages: vec![
+                ProfileEntry {
+                    id: "com.foo".to_string(),
+                    action: PurgeAction::Uninstall,
+                    notes: Some("Preinstalled \"helper\"".to_string()),
+                },
+                ProfileEntry {
+                    id: "com.bar".to_string(),
+                    action: PurgeAction::Suspend,
+                    notes: None,
+                },
+            ],
+        }
+    }
+
+    fn assert_same(a: &Profile, b: &Profile) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.packages.len(), b.packages.len());
+        for (a, b) in a.packages.iter().zip(&b.packages) {
+            assert_eq!((&a.id, a.action, &a.notes), (&b.id, b.action, &b.notes));
+        }
+    }
+
+    #[test]
+    fn profiles_round_trip_through_json_and_toml() {
+        let dir = std::env::temp_dir().join(format!("purge-profile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json = dir.join("profile.json");
+        let toml = dir.join("profile.TOML");
+
+        save(&sample(), &json).unwrap();
+        let from_json = load(&json).unwrap();
+        save(&from_json, &toml).unwrap();
+        assert!(fs::read_to_string(&toml).unwrap().contains("[[packages]]"));
+        let from_toml = load(&toml).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_same(&from_json, &sample());
+        assert_same(&from_toml, &sample());
+    }
+
+    #[test]
+    fn action_defaults_to_uninstall() {
+        let profile: Profile =
+            serde_json::from_str(r#"{ "name": "Old", "packages": [{ "id": "com.foo" }] }"#)
+                .unwrap();
+        assert_eq!(profile.packages[0].action, PurgeAction::Uninstall);
+    }
+
+    #[test]
+    fn diff_reports_each_entry_against_the_device() {
+        let profile = Profile {
+            name: "Mixed".to_string(),
+            packages: ["com.installed", "com.removed", "com.missing", "com.unknown"]
+                .into_iter()
+                .map(|id| ProfileEntry {
+                    id: id.to_string(),
+                    action: PurgeAction::Disable,
+                    notes: None,
+                })
+                .collect(),
+        };
+        let known: HashSet<&str> = ["com.installed", "com.removed", "com.missing"].into();
+        let installed: HashSet<String> = ["com.installed".to_string()].into();
+        let removed: HashSet<String> = ["com.removed".to_string()].into();
+
+        let diffed = diff(&profile, &known, &installed, &removed);
+        let statuses: Vec<(&str, EntryStatus)> = diffed
+            .iter()
+            .map(|(entry, status)| (entry.id.as_str(), *status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("com.installed", EntryStatus::Installed),
+                ("com.removed", EntryStatus::Removed),
+                ("com.missing", EntryStatus::Missing),
+                ("com.unknown", EntryStatus::Unknown),
+            ]
+        );
+        assert!(diffed
+            .iter()
+            .all(|(entry, _)| entry.action == PurgeAction::Disable));
+    }
+}
@@ -0,0 +1,73 @@
+// src/wireless.rs
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A TCP/IP address a device was reached at, kept so it can be reconnected
+/// without typing it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Endpoint {
+    /// `host:port`.
+    pub address: String,
+    /// Unix time in seconds.
+    pub last_connected: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EndpointFile {
+    #[serde(default)]
+    endpoints: Vec<Endpoint>,
+}
+
+pub fn endpoints_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("purge").join("endpoints.json"))
+}
+
+/// Remembered endpoints, most recently connected first.
+pub fn load() -> Result<Vec<Endpoint>, String> {
+    let Some(path) = endpoints_path() else {
+        return Ok(Vec::new());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut file: EndpointFile = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid endpoint file {}: {}", path.display(), e))?;
+    file.endpoints
+        .sort_by_key(|e| std::cmp::Reverse(e.last_connected));
+    Ok(file.endpoints)
+}
+
+fn save(endpoints: Vec<Endpoint>) -> Result<(), String> {
+    let path = endpoints_path().ok_or_else(|| "No config directory available.".to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json =
+        serde_json::to_string_pretty(&EndpointFile { endpoints }).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Records a successful connection to `address`.
+pub fn remember(address: &str) -> Result<(), String> {
+    let mut endpoints = load()?;
+    endpoints.retain(|e| e.address != address);
+    endpoints.push(Endpoint {
+        address: address.to_string(),
+        last_connected: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    });
+    save(endpoints)
+}
+
+pub fn forget(address: &str) -> Result<(), String> {
+    let mut endpoints = load()?;
+    endpoints.retain(|e| e.address != address);
+    save(endpoints)
+}